@@ -2,9 +2,14 @@ use std::process::Command;
 use std::env;
 
 fn main() {
-    println!("foo bar");
+    // Without the tcod backend there are no libtcod libraries to ship
+    if env::var("CARGO_FEATURE_TCOD").is_err() {
+        return;
+    }
+
     let out_dir = env::var("OUT_DIR").unwrap();
-    let libtcod_src_dir = "/Users/bobbylei/Documents/Development/libtcod-mac";
+    let libtcod_src_dir = env::var("LIBTCOD_SRC_DIR")
+        .unwrap_or(String::from("/Users/bobbylei/Documents/Development/libtcod-mac"));
 
     let args = &[
         format!("{}/*.dylib", libtcod_src_dir),
@@ -14,4 +19,4 @@ fn main() {
 
     Command::new("cp").arg(format!("{}/terminal.png", libtcod_src_dir))
                       .arg(format!("{}/../../../", out_dir)).status().unwrap();
-}
\ No newline at end of file
+}
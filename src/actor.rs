@@ -1,22 +1,81 @@
 extern crate core;
 
 use rendering::windows::Windows;
-use movement::{RandomMovementComponent, MovementComponent, UserMovementComponent, AggroMovementComponent};
+use movement::{MovementComponent, UserMovementComponent, Behavior, Action, TURN_ENERGY, NORMAL_SPEED};
 use std::cell::RefCell;
+use std::cmp;
 use std::rc::Rc;
 use rendering::renderers::{Color};
+use combat::Weapon;
+use item::{Inventory, Equipment, DEFAULT_CAPACITY};
 
 use util::Point;
 use game::MoveInfo;
 
 use self::core::ops::Deref;
 
+pub type ActorId = usize;
+
+// What an animal eats; actors without one aren't animals
+#[derive(Copy, Clone, PartialEq)]
+pub enum Diet {
+    Carnivore,
+    Herbivore
+}
+
+impl Diet {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Diet::Carnivore => "carnivore",
+            Diet::Herbivore => "herbivore"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Diet> {
+        match name {
+            "carnivore" => Some(Diet::Carnivore),
+            "herbivore" => Some(Diet::Herbivore),
+            _ => None
+        }
+    }
+}
+
+// How good an actor is in a fight. Accuracy and evasion decide whether a
+// blow lands, strength and defense how much it hurts.
+#[derive(Copy, Clone, PartialEq)]
+pub struct Stats {
+    pub accuracy: i32,
+    pub evasion: i32,
+    pub strength: i32,
+    pub defense: i32
+}
+
+impl Stats {
+    pub fn new(accuracy: i32, evasion: i32, strength: i32, defense: i32) -> Stats {
+        Stats {
+            accuracy: accuracy,
+            evasion: evasion,
+            strength: strength,
+            defense: defense
+        }
+    }
+}
+
 pub struct Actor {
     pub position: Point,
     pub display_char: char,
+    pub name: String,
     movement_component: Box<MovementComponent>,
     pub is_pc: bool,
-    pub health: u8,
+    pub health: i32,
+    pub max_health: i32,
+    pub diet: Option<Diet>,
+    pub stats: Stats,
+    // Energy gained per tick, and how much has been saved up
+    pub speed: i32,
+    pub energy: i32,
+    pub inventory: Inventory,
+    pub equipment: Equipment,
     pub foreground: Color,
     pub background: Color,
 }
@@ -24,60 +83,111 @@ pub struct Actor {
 impl Clone for Actor {
     fn clone(&self) -> Actor {
         let mc = self.movement_component.box_clone();
-        Actor::new(
+        let mut actor = Actor::new(
             self.position.x,
             self.position.y,
             self.display_char,
+            &self.name,
             mc,
             self.is_pc,
             self.foreground,
             self.background,
             self.health
-        )
+        );
+        actor.max_health = self.max_health;
+        actor.diet = self.diet;
+        actor.stats = self.stats;
+        actor.speed = self.speed;
+        actor.energy = self.energy;
+        actor.inventory = self.inventory.clone();
+        actor.equipment = self.equipment.clone();
+        actor
     }
 }
 
 impl Actor {
-    pub fn new(x: i32, y: i32, dc: char, mc: Box<MovementComponent>, is_pc: bool, foreground: Color, background: Color, health: u8) -> Actor {
+    pub fn new(x: i32, y: i32, dc: char, name: &str, mc: Box<MovementComponent>, is_pc: bool, foreground: Color, background: Color, health: i32) -> Actor {
         Actor {
             position: Point {x: x, y: y},
             display_char: dc,
+            name: String::from(name),
             movement_component: mc,
             is_pc: is_pc,
             health: health,
+            max_health: health,
+            diet: None,
+            stats: Stats::new(0, 0, 0, 0),
+            speed: NORMAL_SPEED,
+            energy: TURN_ENERGY,
+            inventory: Inventory::new(DEFAULT_CAPACITY),
+            equipment: Equipment::new(),
             foreground: foreground,
             background: background
          }
     }
 
-    pub fn update(&mut self, windows: &mut Windows) {
-        self.position = self.movement_component.update(self.position, windows);
+    // Moves the actor, or returns the point it wants to attack. Walking into
+    // another actor counts as attacking it. Either way it costs energy.
+    pub fn update(&mut self, windows: &mut Windows) -> Option<Point> {
+        let action = self.movement_component.update(self.position, windows);
+        self.energy -= action.cost(self.position);
+        match action {
+            Action::Move(point) => {
+                self.position = point;
+                None
+            },
+            Action::Attack(point) => Some(point),
+            Action::Blocked => None
+        }
     }
 
-    // pub fn render(&self, rendering_component: &mut Box<RenderingComponent>) {
-    //     rendering_component.render_object(self.position, self.display_char);
-    // }
+    pub fn is_ready(&self) -> bool {
+        self.energy >= TURN_ENERGY
+    }
 
-    pub fn dog(x: i32, y: i32, move_info: Rc<RefCell<MoveInfo>>) -> Actor {
-        let mc: Box<MovementComponent> = Box::new(RandomMovementComponent::new(move_info));
-        Actor::new(x, y, 'd', mc, false, Color::White, Color::Black, 20u8)
+    // A speed of 0 never saves up anything, so the actor never acts
+    pub fn gain_energy(&mut self) {
+        self.energy += cmp::max(0, self.speed);
     }
 
-    pub fn cat(x: i32, y: i32, move_info: Rc<RefCell<MoveInfo>>) -> Actor {
-        let mc: Box<MovementComponent> = Box::new(RandomMovementComponent::new(move_info));
-        Actor::new(x, y, 'c', mc, false, Color::White, Color::Black, 20u8)
+    pub fn weapon(&self) -> Option<&Box<Weapon>> {
+        self.equipment.weapon()
     }
 
+    pub fn behavior(&self) -> Behavior {
+        self.movement_component.behavior()
+    }
+
+    pub fn set_movement_component(&mut self, mc: Box<MovementComponent>) {
+        self.movement_component = mc;
+    }
+
+    pub fn take_damage(&mut self, damage: u16) {
+        self.health -= damage as i32;
+    }
+
+    // Returns how much was actually healed
+    pub fn heal(&mut self, amount: i32) -> i32 {
+        let healed = cmp::min(amount, self.max_health - self.health);
+        self.health += healed;
+        healed
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.health <= 0
+    }
+
+    // pub fn render(&self, rendering_component: &mut Box<RenderingComponent>) {
+    //     rendering_component.render_object(self.position, self.display_char);
+    // }
+
     pub fn heroine(move_info: Rc<RefCell<MoveInfo>>) -> Actor {
         let point = {
             move_info.borrow().deref().char_location
         };
         let mc: Box<MovementComponent> = Box::new(UserMovementComponent::new(move_info));
-        Actor::new(point.x, point.y, '@', mc, true, Color::Blue, Color::Black, 20u8)
-    }
-
-    pub fn kobold(x: i32, y: i32, move_info: Rc<RefCell<MoveInfo>>) -> Actor {
-        let mc: Box<MovementComponent> = Box::new(AggroMovementComponent::new(move_info));
-        Actor::new(x, y, 'k', mc, false, Color::Red, Color::Black, 20u8)
+        let mut heroine = Actor::new(point.x, point.y, '@', "heroine", mc, true, Color::Blue, Color::Black, 20);
+        heroine.stats = Stats::new(2, 2, 1, 0);
+        heroine
     }
-}
\ No newline at end of file
+}
@@ -2,69 +2,434 @@ extern crate rand;
 extern crate core;
 
 use self::rand::distributions::{IndependentSample, Range};
+use std::cmp;
 
-use actor::Actor;
+use actor::Stats;
+use item::Item;
+use rng::GameRng;
+use util::{Point, line};
 
 pub trait Weapon {
     fn get_name(&self) -> String;
-    fn deal_damage(&self, &Box<Actor>) -> u16;
+    fn deal_damage(&self, &mut GameRng) -> u16;
+    fn box_clone(&self) -> Box<Weapon>;
+
+    // Thrown weapons are aimed at a tile instead of swung in a direction
+    fn is_thrown(&self) -> bool { false }
+
+    // Ranged weapons fly off in the chosen direction instead of hitting
+    // the tile next to the attacker
+    fn is_ranged(&self) -> bool { false }
+
+    // How many tiles away it can reach
+    fn range(&self) -> i32 { 1 }
+}
+
+// An actor on the map going for whatever stands at `target`
+pub struct Attack {
+    pub attacker: String,
+    pub stats: Stats,
+    pub target: Point
+}
+
+pub const BASE_HIT_CHANCE: i32 = 75;
+pub const CRITICAL_CHANCE: i32 = 5;
+
+pub enum AttackOutcome {
+    Miss,
+    Hit(u16),
+    Critical(u16)
 }
 
-pub struct Boomerang {
+// Rolls to hit, every point of accuracy over the defender's evasion making
+// a hit more likely. Strength adds to the weapon's damage and defense takes
+// off it, except on a critical hit, which goes through armour and does
+// double damage.
+pub fn resolve_attack(attacker: &Stats, defender: &Stats, weapon: &Weapon, rng: &mut GameRng) -> AttackOutcome {
+    let hit_chance = cmp::max(5, cmp::min(95, BASE_HIT_CHANCE + 5 * (attacker.accuracy - defender.evasion)));
+    let roll = Range::new(0, 100).ind_sample(rng);
+    if roll >= hit_chance {
+        return AttackOutcome::Miss;
+    }
+
+    let damage = (weapon.deal_damage(rng) as i32).saturating_add(attacker.strength);
+    if roll < CRITICAL_CHANCE + cmp::max(0, attacker.accuracy) {
+        AttackOutcome::Critical(cmp::min(cmp::max(1, damage.saturating_mul(2)), u16::MAX as i32) as u16)
+    } else {
+        AttackOutcome::Hit(cmp::min(cmp::max(1, damage.saturating_sub(defender.defense)), u16::MAX as i32) as u16)
+    }
+}
+
+// Damage written the tabletop way: `2d4+1` is two four-sided dice plus one.
+// A plain number is a fixed amount.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Dice {
+    pub count: u32,
+    pub sides: u32,
+    pub bonus: i32
+}
+
+// More dice than this in one roll is a typo, not a weapon
+pub const MAX_DICE: i32 = 100;
+
+impl Dice {
+    pub fn new(count: u32, sides: u32, bonus: i32) -> Dice {
+        Dice {
+            count: count,
+            sides: sides,
+            bonus: bonus
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Dice> {
+        let (dice, bonus) = match name.find(|c| c == '+' || c == '-') {
+            Some(index) => (&name[..index], name[index..].parse::<i32>().ok()?),
+            None => (name, 0)
+        };
+        match dice.find('d') {
+            Some(index) => {
+                // Anything that doesn't fit an i32 can't be rolled
+                let count = dice[..index].parse::<i32>().ok()?;
+                let sides = dice[index + 1..].parse::<i32>().ok()?;
+                if count < 0 || count > MAX_DICE || sides <= 0 || sides == i32::MAX {
+                    None
+                } else {
+                    Some(Dice::new(count as u32, sides as u32, bonus))
+                }
+            },
+            None if dice.is_empty() => None,
+            None => Some(Dice::new(0, 1, dice.parse::<i32>().ok()?.checked_add(bonus)?))
+        }
+    }
+
+    pub fn roll(&self, rng: &mut GameRng) -> u16 {
+        let sides = cmp::min(self.sides, i32::MAX as u32 - 1) as i32;
+        let mut total = self.bonus;
+        for _ in 0..self.count {
+            total = total.saturating_add(Range::new(1, sides + 1).ind_sample(rng));
+        }
+        cmp::min(cmp::max(0, total), u16::MAX as i32) as u16
+    }
+}
+
+// A weapon as given by an item definition, see `item::ItemRegistry`
+pub struct DefinedWeapon {
     name: String,
-    base_damage: u8
+    damage: Dice,
+    range: i32,
+    thrown: bool,
+    ranged: bool
 }
 
-impl Boomerang {
-    pub fn new() -> Boomerang {
-        Boomerang {
-            name: String::from("Little Boomerang"),
-            base_damage: 13
+impl DefinedWeapon {
+    pub fn new(name: &str, damage: Dice, range: i32, thrown: bool, ranged: bool) -> DefinedWeapon {
+        DefinedWeapon {
+            name: String::from(name),
+            damage: damage,
+            range: range,
+            thrown: thrown,
+            ranged: ranged
         }
     }
 }
 
-impl Weapon for Boomerang {
-    fn get_name(&self) -> String {
-        self.name.clone()
+impl Weapon for DefinedWeapon {
+    fn get_name(&self) -> String { self.name.clone() }
+
+    fn deal_damage(&self, rng: &mut GameRng) -> u16 {
+        self.damage.roll(rng)
     }
 
-    fn deal_damage(&self, _: &Box<Actor>) -> u16 {
-        self.base_damage as u16
+    fn box_clone(&self) -> Box<Weapon> {
+        Box::new(DefinedWeapon::new(&self.name, self.damage, self.range, self.thrown, self.ranged))
     }
+
+    fn is_thrown(&self) -> bool { self.thrown }
+
+    fn is_ranged(&self) -> bool { self.ranged }
+
+    fn range(&self) -> i32 { self.range }
 }
 
-pub struct Bomb {
-    name: String,
-    base_damage: u8
+// Tiles flown per turn
+pub const BOOMERANG_SPEED: u32 = 3;
+
+// A boomerang in the air. It flies out to the turn point, or until it hits
+// someone, then heads back to where it was thrown from.
+pub struct FlyingBoomerang {
+    pub item: Item,
+    pub position: Point,
+    pub origin: Point,
+    pub turn_point: Point,
+    pub returning: bool,
+    // The line it is flying along, worked out once for each way, and how
+    // far along it it is
+    path: Vec<Point>,
+    step: usize
+}
+
+impl FlyingBoomerang {
+    // Sends it off from `origin` in the direction of the offset, as far as
+    // the weapon's range
+    pub fn new(item: Item, origin: Point, direction: Point) -> FlyingBoomerang {
+        let range = item.weapon.as_ref().map_or(1, |weapon| weapon.range());
+        let turn_point = Point::new(origin.x + direction.x * range, origin.y + direction.y * range);
+        FlyingBoomerang::in_flight(item, origin, origin, turn_point, false)
+    }
+
+    // Picks up a flight somewhere along the way, e.g. from a save
+    pub fn in_flight(item: Item, position: Point, origin: Point, turn_point: Point, returning: bool) -> FlyingBoomerang {
+        let to = if returning { origin } else { turn_point };
+        FlyingBoomerang {
+            item: item,
+            position: position,
+            origin: origin,
+            turn_point: turn_point,
+            returning: returning,
+            path: line(position, to),
+            step: 0
+        }
+    }
+
+    pub fn next_point(&self) -> Point {
+        match self.path.get(self.step + 1) {
+            Some(point) => *point,
+            None => self.position
+        }
+    }
+
+    pub fn advance(&mut self) {
+        self.position = self.next_point();
+        if self.step + 1 < self.path.len() {
+            self.step += 1;
+        }
+    }
+
+    pub fn has_arrived(&self) -> bool {
+        self.step + 1 >= self.path.len()
+    }
+
+    // Heads back to where it was thrown from
+    pub fn turn_back(&mut self) {
+        self.returning = true;
+        self.path = line(self.position, self.origin);
+        self.step = 0;
+    }
+}
+
+pub const BOMB_FUSE: u32 = 3;
+pub const BOMB_RADIUS: i32 = 2;
+
+// A bomb lying on the map, going off once the fuse has burnt down
+pub struct LitBomb {
+    pub position: Point,
+    pub fuse: u32,
+    pub damage: u16,
+    pub radius: i32
+}
+
+impl LitBomb {
+    pub fn new(position: Point, damage: u16) -> LitBomb {
+        LitBomb {
+            position: position,
+            fuse: BOMB_FUSE,
+            damage: damage,
+            radius: BOMB_RADIUS
+        }
+    }
+
+    // Full damage in the middle, less towards the edge of the blast
+    pub fn damage_at(&self, distance: u32) -> u16 {
+        let distance = distance as i32;
+        if distance > self.radius {
+            return 0;
+        }
+        let damage = self.damage as i32 * (self.radius + 1 - distance) / (self.radius + 1);
+        if damage < 1 { 1 } else { damage as u16 }
+    }
 }
 
-pub struct Sword {
+// For when the heroine has nothing in her hands
+pub struct Fists {
     name: String,
     base_damage: u8
 }
 
-impl Sword {
-    pub fn new() -> Sword {
-        Sword {
-            name: String::from("Heroic Sworc"),
-            base_damage: 4
+impl Fists {
+    pub fn new() -> Fists {
+        Fists {
+            name: String::from("bare hands"),
+            base_damage: 2
         }
     }
 }
 
-impl Weapon for Sword {
+impl Default for Fists {
+    fn default() -> Fists {
+        Fists::new()
+    }
+}
+
+impl Weapon for Fists {
     fn get_name(&self) -> String { self.name.clone() }
 
-    fn deal_damage(&self, enemy: &Box<Actor>) -> u16 {
-        let x   = enemy.health % 10u8;
-        let max = self.base_damage + (x * x);
-        let mut rng = rand::thread_rng();
-        Range::new(0u8, max).ind_sample(&mut rng) as u16
+    fn deal_damage(&self, rng: &mut GameRng) -> u16 {
+        Range::new(1u8, self.base_damage + 1).ind_sample(rng) as u16
     }
+
+    fn box_clone(&self) -> Box<Weapon> { Box::new(Fists::new()) }
 }
 
-pub struct Lettuce {
+// Teeth and claws, for monsters that don't carry anything
+pub struct Claws {
     name: String,
     base_damage: u8
-}
\ No newline at end of file
+}
+
+impl Claws {
+    pub fn new() -> Claws {
+        Claws {
+            name: String::from("Claws"),
+            base_damage: 3
+        }
+    }
+}
+
+impl Default for Claws {
+    fn default() -> Claws {
+        Claws::new()
+    }
+}
+
+impl Weapon for Claws {
+    fn get_name(&self) -> String { self.name.clone() }
+
+    fn deal_damage(&self, rng: &mut GameRng) -> u16 {
+        Range::new(1u8, self.base_damage + 1).ind_sample(rng) as u16
+    }
+
+    fn box_clone(&self) -> Box<Weapon> { Box::new(Claws::new()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use item::ItemRegistry;
+
+    #[test]
+    fn dice_from_name() {
+        assert_eq!(Dice::from_name("d6"), None);
+        assert_eq!(Dice::from_name("1d4+"), None);
+        assert_eq!(Dice::from_name("-3"), None);
+        assert_eq!(Dice::from_name("1d0"), None);
+        assert_eq!(Dice::from_name("2147483647d6"), None);
+        assert_eq!(Dice::from_name("101d6"), None);
+        assert_eq!(Dice::from_name("1d99999999999"), None);
+        assert_eq!(Dice::from_name("1d2147483647"), None);
+        assert_eq!(Dice::from_name("2d4+1"), Some(Dice::new(2, 4, 1)));
+        assert_eq!(Dice::from_name("100d6-2"), Some(Dice::new(100, 6, -2)));
+        assert_eq!(Dice::from_name("12"), Some(Dice::new(0, 1, 12)));
+    }
+
+    #[test]
+    fn dice_rolls_stay_in_range() {
+        let mut rng = GameRng::new(3);
+        let dice = Dice::from_name("2d4+1").unwrap();
+        for _ in 0..1000 {
+            let roll = dice.roll(&mut rng);
+            assert!(roll >= 3 && roll <= 9);
+        }
+        assert_eq!(Dice::new(1, 6, -10).roll(&mut rng), 0);
+        assert_eq!(Dice::from_name("12").unwrap().roll(&mut rng), 12);
+    }
+
+    // A club that always does 4, so only the attack roll is left to chance
+    fn club() -> DefinedWeapon {
+        DefinedWeapon::new("club", Dice::new(0, 1, 4), 1, false, false)
+    }
+
+    // How many of `rolls` attacks miss, hit and crit, and the damage each kind did
+    fn outcomes(attacker: Stats, defender: Stats, rolls: u32, seed: u32) -> (u32, Vec<u16>, Vec<u16>) {
+        let mut rng = GameRng::new(seed);
+        let (mut misses, mut hits, mut crits) = (0, vec![], vec![]);
+        for _ in 0..rolls {
+            match resolve_attack(&attacker, &defender, &club(), &mut rng) {
+                AttackOutcome::Miss => misses += 1,
+                AttackOutcome::Hit(damage) => hits.push(damage),
+                AttackOutcome::Critical(damage) => crits.push(damage)
+            }
+        }
+        (misses, hits, crits)
+    }
+
+    #[test]
+    fn hits_misses_and_crits() {
+        let even = Stats::new(0, 0, 1, 2);
+        let (misses, hits, crits) = outcomes(even, even, 1000, 7);
+        assert_eq!((misses, hits.clone(), crits.clone()), outcomes(even, even, 1000, 7));
+
+        // 75% to hit, 5% of it critical
+        assert!(misses > 200 && misses < 300, "{} misses", misses);
+        assert!(crits.len() > 25 && crits.len() < 80, "{} crits", crits.len());
+        assert!(hits.iter().all(|&damage| damage == 3));
+        assert!(crits.iter().all(|&damage| damage == 10));
+    }
+
+    #[test]
+    fn hit_chance_is_clamped() {
+        let (misses, hits, crits) = outcomes(Stats::new(100, 0, 0, 0), Stats::new(0, 0, 0, 0), 2000, 11);
+        assert_eq!(hits.len() + crits.len() + misses as usize, 2000);
+        assert!(misses > 50 && misses < 150, "{} misses", misses);
+
+        let (misses, hits, crits) = outcomes(Stats::new(0, 0, 0, 0), Stats::new(0, 100, 0, 0), 2000, 11);
+        let landed = hits.len() + crits.len();
+        assert_eq!(misses as usize + landed, 2000);
+        assert!(landed > 50 && landed < 150, "{} landed", landed);
+    }
+
+    #[test]
+    fn crits_ignore_defense() {
+        // Accuracy past the hit chance makes every hit a critical one
+        let (_, hits, crits) = outcomes(Stats::new(100, 0, 0, 0), Stats::new(0, 0, 0, 50), 200, 5);
+        assert!(hits.is_empty());
+        assert!(!crits.is_empty());
+        assert!(crits.iter().all(|&damage| damage == 8));
+
+        let (_, hits, _) = outcomes(Stats::new(0, 0, 0, 0), Stats::new(0, 0, 0, 50), 200, 5);
+        assert!(!hits.is_empty());
+        assert!(hits.iter().all(|&damage| damage == 1));
+    }
+
+    #[test]
+    fn huge_damage_saturates() {
+        let maul = DefinedWeapon::new("maul", Dice::new(0, 1, 60000), 1, false, false);
+        let mut rng = GameRng::new(5);
+        for &accuracy in [0, 100].iter() {
+            let attacker = Stats::new(accuracy, 0, i32::MAX, 0);
+            for _ in 0..100 {
+                match resolve_attack(&attacker, &Stats::new(0, 0, 0, -5), &maul, &mut rng) {
+                    AttackOutcome::Miss => {},
+                    AttackOutcome::Hit(damage) | AttackOutcome::Critical(damage) => assert_eq!(damage, u16::MAX)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn boomerangs_fly_out_and_back() {
+        let items = ItemRegistry::parse("item stick\ndamage 1\nrange 3\neffect returns\n").unwrap();
+        let item = items.create("stick").unwrap();
+        let mut boomerang = FlyingBoomerang::new(item, Point::new(5, 5), Point::new(1, -1));
+        let mut flight = vec![];
+        for _ in 0..6 {
+            if boomerang.has_arrived() {
+                boomerang.turn_back();
+            }
+            boomerang.advance();
+            flight.push((boomerang.position.x, boomerang.position.y));
+        }
+        assert_eq!(flight, vec![(6, 4), (7, 3), (8, 2), (7, 3), (6, 4), (5, 5)]);
+        assert!(boomerang.returning);
+        assert!(boomerang.has_arrived());
+    }
+}
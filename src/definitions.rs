@@ -0,0 +1,169 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+
+// Definition files under `data/`, and save files, are made of
+// `record value...` lines. Blank lines and lines starting with `#` are
+// skipped.
+pub struct Record<'a> {
+    // Where it came from, for error messages
+    pub at: String,
+    pub name: &'a str,
+    pub rest: &'a str,
+    pub fields: Vec<&'a str>
+}
+
+pub fn records<'a>(contents: &'a str) -> Vec<Record<'a>> {
+    let mut records = vec![];
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, rest) = match line.find(' ') {
+            Some(index) => (&line[..index], line[index + 1..].trim()),
+            None => (line, "")
+        };
+        records.push(Record {
+            at: format!("line {}", number + 1),
+            name: name,
+            rest: rest,
+            fields: rest.split_whitespace().collect()
+        });
+    }
+    records
+}
+
+// Each definition is a block starting with a `<header> <name>` record, e.g.
+// `monster dog`, followed by records that fill it in. `new` makes the
+// definition from its name, `field` reads one record of its block into it.
+// Names must be unique: a second block with the same name would never be
+// looked up.
+pub fn blocks<T, N, F>(contents: &str, header: &str, new: N, field: F) -> Result<Vec<T>, String>
+    where N: Fn(&str) -> T, F: Fn(&mut T, &Record) -> Result<(), String>
+{
+    let mut defs: Vec<T> = vec![];
+    let mut names: Vec<&str> = vec![];
+    for record in records(contents).iter() {
+        if record.name == header {
+            if record.rest.is_empty() {
+                return Err(format!("{} without a name on {}", header, record.at));
+            }
+            if names.contains(&record.rest) {
+                return Err(format!("{} '{}' on {} is already defined", header, record.rest, record.at));
+            }
+            names.push(record.rest);
+            defs.push(new(record.rest));
+            continue;
+        }
+        match defs.last_mut() {
+            Some(def) => field(def, record)?,
+            None => { return Err(format!("'{}' before any {} on {}", record.name, header, record.at)); }
+        }
+    }
+    Ok(defs)
+}
+
+// Reads definitions from `path`, relative to where the game is run from,
+// so they can be changed without a rebuild. If the file is broken, or not
+// there at all, the `builtin` copy compiled into the game is used instead;
+// anything worth telling the player is added to `problems`.
+pub fn load_or_builtin<T, F>(path: &Path, builtin: &str, parse: F, problems: &mut Vec<String>) -> T
+    where T: Default, F: Fn(&str) -> Result<T, String>
+{
+    let mut contents = String::new();
+    if File::open(path).and_then(|mut f| f.read_to_string(&mut contents)).is_ok() {
+        match parse(&contents) {
+            Ok(definitions) => { return definitions; },
+            Err(e) => problems.push(format!("{}: {}", path.display(), e))
+        }
+    }
+    match parse(builtin) {
+        Ok(definitions) => definitions,
+        Err(e) => {
+            problems.push(format!("Built-in {}: {}", path.display(), e));
+            T::default()
+        }
+    }
+}
+
+impl<'a> Record<'a> {
+    pub fn field<T: FromStr>(&self, index: usize) -> Result<T, String> {
+        match self.fields.get(index).and_then(|f| f.parse::<T>().ok()) {
+            Some(value) => Ok(value),
+            None => Err(format!("Bad number on {}", self.at))
+        }
+    }
+
+    pub fn named<T, F: Fn(&str) -> Option<T>>(&self, index: usize, from_name: F) -> Result<T, String> {
+        match self.fields.get(index).and_then(|f| from_name(f)) {
+            Some(value) => Ok(value),
+            None => Err(format!("Bad value '{}' on {}", self.fields.get(index).map_or("", |f| *f), self.at))
+        }
+    }
+
+    pub fn unknown(&self) -> String {
+        format!("Unknown record '{}' on {}", self.name, self.at)
+    }
+
+    // A single character, like a glyph
+    pub fn char(&self) -> Result<char, String> {
+        let mut chars = self.rest.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(format!("Bad character on {}", self.at))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Thing {
+        name: String,
+        size: i32
+    }
+
+    fn things(contents: &str) -> Result<Vec<Thing>, String> {
+        blocks(contents, "thing", |name| Thing { name: String::from(name), size: 0 }, |thing, record| {
+            match record.name {
+                "size" => thing.size = record.field(0)?,
+                _ => { return Err(record.unknown()); }
+            }
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn reads_blocks() {
+        let things = things("# Things\n\nthing big rock\nsize 3\n  # indented comment\nthing pebble\n").unwrap();
+        assert_eq!(things.len(), 2);
+        assert_eq!((things[0].name.as_str(), things[0].size), ("big rock", 3));
+        assert_eq!((things[1].name.as_str(), things[1].size), ("pebble", 0));
+    }
+
+    #[test]
+    fn unknown_record() {
+        assert_eq!(things("thing rock\nweight 3\n").err().unwrap(), "Unknown record 'weight' on line 2");
+    }
+
+    #[test]
+    fn field_before_the_header() {
+        assert_eq!(things("size 3\nthing rock\n").err().unwrap(), "'size' before any thing on line 1");
+        assert_eq!(things("thing\n").err().unwrap(), "thing without a name on line 1");
+    }
+
+    #[test]
+    fn names_are_unique() {
+        assert_eq!(things("thing rock\nsize 3\nthing pebble\nthing rock\nsize 4\n").err().unwrap(),
+                   "thing 'rock' on line 4 is already defined");
+    }
+
+    #[test]
+    fn bad_values() {
+        assert_eq!(things("thing rock\nsize huge\n").err().unwrap(), "Bad number on line 2");
+        assert_eq!(things("thing rock\nsize\n").err().unwrap(), "Bad number on line 2");
+    }
+}
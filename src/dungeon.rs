@@ -0,0 +1,262 @@
+extern crate rand;
+extern crate core;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use self::rand::Rng;
+
+use game::MoveInfo;
+use item::ItemRegistry;
+use map::Maps;
+use monsters::{MonsterRegistry, Faction};
+use rng::GameRng;
+use terrain::{Terrain, Tile};
+use util::{Point, Bound};
+
+use self::core::ops::DerefMut;
+
+// Leaves of the partition are never split below this, in tiles
+const MIN_LEAF_SIZE: i32 = 10;
+const MIN_ROOM_SIZE: i32 = 4;
+// Random spots tried before deciding the rooms are full
+const FREE_POINT_ATTEMPTS: u32 = 200;
+
+// Carves rooms and corridors into the terrain by binary space partitioning
+// and returns the rooms in the order they were carved. Room bounds follow
+// `Bound::contains`: the max corner is one past the last floor tile.
+pub fn generate(terrain: &mut Terrain, rng: &mut GameRng) -> Vec<Bound> {
+  let size = terrain.size;
+  terrain.fill(Tile::Wall);
+
+  let mut rooms = vec![];
+  split(Bound::new(0, 0, size.max.x, size.max.y), terrain, rng, &mut rooms);
+  rooms
+}
+
+// Builds a fresh level at the depth of `maps`: terrain, stairs and the
+// creatures and items scattered through the rooms. The heroine starts in the first
+// room, on the way up if there is one; the way down is in the last room.
+// Deeper levels have more and tougher enemies; which creatures can turn up
+// where is up to the monster definitions.
+pub fn generate_level(maps: &mut Maps, move_info: Rc<RefCell<MoveInfo>>, monsters: &MonsterRegistry, items: &ItemRegistry) {
+  let depth = maps.depth;
+  let rooms = {
+    let mut mi = move_info.borrow_mut();
+    let mi = mi.deref_mut();
+    let rooms = generate(&mut mi.terrain.borrow_mut(), &mut mi.rng);
+    mi.char_location = rooms[0].center();
+    rooms
+  };
+
+  let start = rooms[0].center();
+  let stairs_down = rooms[rooms.len() - 1].center();
+  {
+    let mut terrain = maps.terrain.borrow_mut();
+    if depth > 1 {
+      terrain.set_tile(start, Tile::StairsUp);
+    }
+    terrain.set_tile(stairs_down, Tile::StairsDown);
+  }
+
+  let other_rooms: Vec<Bound> = if rooms.len() > 1 { rooms[1..].to_vec() } else { rooms.clone() };
+  let mut taken = vec![start, stairs_down];
+
+  for def in monsters.spawnable(Faction::Friend, depth).iter() {
+    if let Some(point) = free_point(&other_rooms, &mut taken, &move_info) {
+      maps.spawn(monsters, &def.name, point);
+    }
+  }
+
+  let kinds = monsters.spawnable(Faction::Enemy, depth);
+  if !kinds.is_empty() {
+    let enemies = (other_rooms.len() / 4) + depth as usize + move_info.borrow_mut().deref_mut().rng.gen_range(0, 3);
+    for _ in 0..enemies {
+      let point = match free_point(&other_rooms, &mut taken, &move_info) {
+        Some(point) => point,
+        // The rooms are full
+        None => { break; }
+      };
+      let kind = move_info.borrow_mut().deref_mut().rng.gen_range(0, kinds.len());
+      maps.spawn(monsters, &kinds[kind].name, point);
+    }
+  }
+
+  // Something to find on every level
+  if !items.defs().is_empty() {
+    if let Some(point) = free_point(&other_rooms, &mut taken, &move_info) {
+      let kind = move_info.borrow_mut().deref_mut().rng.gen_range(0, items.defs().len());
+      maps.items.put(point, items.defs()[kind].create());
+    }
+  }
+}
+
+fn split(area: Bound, terrain: &mut Terrain, rng: &mut GameRng, rooms: &mut Vec<Bound>) {
+  let width = area.max.x - area.min.x;
+  let height = area.max.y - area.min.y;
+  let can_split_x = width >= MIN_LEAF_SIZE * 2;
+  let can_split_y = height >= MIN_LEAF_SIZE * 2;
+
+  if !can_split_x && !can_split_y {
+    rooms.push(carve_room(area, terrain, rng));
+    return;
+  }
+
+  // Prefer cutting across the long side so leaves stay roughly square
+  let split_x = if can_split_x && can_split_y {
+    if width > height { true } else if height > width { false } else { rng.gen() }
+  } else {
+    can_split_x
+  };
+
+  let (first, second) = if split_x {
+    let at = area.min.x + rng.gen_range(MIN_LEAF_SIZE, width - MIN_LEAF_SIZE + 1);
+    (Bound::new(area.min.x, area.min.y, at, area.max.y), Bound::new(at, area.min.y, area.max.x, area.max.y))
+  } else {
+    let at = area.min.y + rng.gen_range(MIN_LEAF_SIZE, height - MIN_LEAF_SIZE + 1);
+    (Bound::new(area.min.x, area.min.y, area.max.x, at), Bound::new(area.min.x, at, area.max.x, area.max.y))
+  };
+
+  let first_start = rooms.len();
+  split(first, terrain, rng, rooms);
+  let second_start = rooms.len();
+  split(second, terrain, rng, rooms);
+
+  // Join the two halves with a corridor between a room on each side
+  let from = rooms[rng.gen_range(first_start, second_start)].center();
+  let to = rooms[rng.gen_range(second_start, rooms.len())].center();
+  carve_corridor(from, to, terrain, rng);
+}
+
+fn carve_room(leaf: Bound, terrain: &mut Terrain, rng: &mut GameRng) -> Bound {
+  // Leave at least one tile of wall between the room and the leaf's edge
+  let width = rng.gen_range(MIN_ROOM_SIZE, leaf.max.x - leaf.min.x - 1);
+  let height = rng.gen_range(MIN_ROOM_SIZE, leaf.max.y - leaf.min.y - 1);
+  let x = rng.gen_range(leaf.min.x + 1, leaf.max.x - width);
+  let y = rng.gen_range(leaf.min.y + 1, leaf.max.y - height);
+
+  let room = Bound::new(x, y, x + width, y + height);
+  for x in room.min.x..room.max.x {
+    for y in room.min.y..room.max.y {
+      terrain.set_tile(Point::new(x, y), Tile::Floor);
+    }
+  }
+  room
+}
+
+fn carve_corridor(from: Point, to: Point, terrain: &mut Terrain, rng: &mut GameRng) {
+  let corner = if rng.gen() { Point::new(to.x, from.y) } else { Point::new(from.x, to.y) };
+  carve_line(from, corner, terrain);
+  carve_line(corner, to, terrain);
+}
+
+// Only for straight horizontal or vertical lines
+fn carve_line(from: Point, to: Point, terrain: &mut Terrain) {
+  let (min_x, max_x) = if from.x < to.x { (from.x, to.x) } else { (to.x, from.x) };
+  let (min_y, max_y) = if from.y < to.y { (from.y, to.y) } else { (to.y, from.y) };
+  for x in min_x..(max_x + 1) {
+    for y in min_y..(max_y + 1) {
+      terrain.set_tile(Point::new(x, y), Tile::Floor);
+    }
+  }
+}
+
+// A random spot in one of the rooms that nothing has been put on yet, or
+// None if there doesn't seem to be one left
+fn free_point(rooms: &[Bound], taken: &mut Vec<Point>, move_info: &Rc<RefCell<MoveInfo>>) -> Option<Point> {
+  if rooms.is_empty() {
+    return None;
+  }
+  let mut mi = move_info.borrow_mut();
+  let rng = &mut mi.deref_mut().rng;
+  for _ in 0..FREE_POINT_ATTEMPTS {
+    let room = rooms[rng.gen_range(0, rooms.len())];
+    let point = Point::new(rng.gen_range(room.min.x, room.max.x), rng.gen_range(room.min.y, room.max.y));
+    if !taken.iter().any(|p| p.x == point.x && p.y == point.y) {
+      taken.push(point);
+      return Some(point);
+    }
+  }
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::VecDeque;
+
+  fn level(seed: u32) -> (Terrain, Vec<Bound>) {
+    let mut terrain = Terrain::new(Bound::new(0, 0, 78, 49));
+    let rooms = generate(&mut terrain, &mut GameRng::new(seed));
+    (terrain, rooms)
+  }
+
+  fn tiles(terrain: &Terrain) -> String {
+    (0..terrain.size.max.y).flat_map(|y| (0..terrain.size.max.x).map(move |x| Point::new(x, y)))
+                           .map(|point| terrain.tile_at(point).display_char())
+                           .collect()
+  }
+
+  fn corners(rooms: &[Bound]) -> Vec<(i32, i32, i32, i32)> {
+    rooms.iter().map(|room| (room.min.x, room.min.y, room.max.x, room.max.y)).collect()
+  }
+
+  #[test]
+  fn same_seed_same_level() {
+    let (first, first_rooms) = level(99);
+    let (second, second_rooms) = level(99);
+    assert_eq!(tiles(&first), tiles(&second));
+    assert_eq!(corners(&first_rooms), corners(&second_rooms));
+
+    let (other, _) = level(100);
+    assert!(tiles(&first) != tiles(&other));
+  }
+
+  #[test]
+  fn every_room_is_reachable_from_the_start() {
+    for seed in 0..50 {
+      let (terrain, rooms) = level(seed);
+      assert!(rooms.len() > 1);
+      let size = terrain.size;
+
+      // Flood fill over the floor the way the heroine walks
+      let mut reached = vec![false; size.cell_count()];
+      let mut queue = VecDeque::new();
+      queue.push_back(rooms[0].center());
+      while let Some(point) = queue.pop_front() {
+        let cell = match size.cell_index(point) {
+          Some(cell) => cell,
+          None => continue
+        };
+        if reached[cell] || terrain.is_blocked(point) {
+          continue;
+        }
+        reached[cell] = true;
+        for &(dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)].iter() {
+          queue.push_back(point.offset(Point::new(dx, dy)));
+        }
+      }
+
+      for room in rooms.iter() {
+        for x in room.min.x..room.max.x {
+          for y in room.min.y..room.max.y {
+            assert!(reached[size.cell_index(Point::new(x, y)).unwrap()], "seed {}: ({}, {}) can't be reached", seed, x, y);
+          }
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn free_point_gives_up_when_the_rooms_are_full() {
+    let move_info = Rc::new(RefCell::new(MoveInfo::new(Bound::new(0, 0, 78, 49), GameRng::new(1))));
+    let rooms = [Bound::new(3, 3, 5, 4)];
+    let mut taken = vec![Point::new(3, 3)];
+
+    assert!(free_point(&rooms, &mut taken, &move_info) == Some(Point::new(4, 3)));
+    assert_eq!(taken.len(), 2);
+    assert!(free_point(&rooms, &mut taken, &move_info).is_none());
+    assert_eq!(taken.len(), 2);
+    assert!(free_point(&[], &mut taken, &move_info).is_none());
+  }
+}
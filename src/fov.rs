@@ -0,0 +1,134 @@
+use terrain::Terrain;
+use util::{Point, Bound, line};
+
+pub const DEFAULT_FOV_RADIUS: i32 = 8;
+
+// What the heroine can see right now, and every tile she has ever seen
+pub struct FieldOfView {
+    pub radius: i32,
+    size: Bound,
+    visible: Vec<bool>,
+    explored: Vec<bool>
+}
+
+impl FieldOfView {
+    pub fn new(size: Bound, radius: i32) -> FieldOfView {
+        FieldOfView {
+            radius: radius,
+            size: size,
+            visible: vec![false; size.cell_count()],
+            explored: vec![false; size.cell_count()]
+        }
+    }
+
+    // Casts a ray from the origin to every point on the edge of the square
+    // around it; a ray stops at the first tile that blocks sight, but that
+    // tile is still seen.
+    pub fn compute(&mut self, origin: Point, terrain: &Terrain) {
+        for seen in self.visible.iter_mut() {
+            *seen = false;
+        }
+        self.mark_visible(origin);
+
+        let r = self.radius;
+        let mut edge = vec![];
+        for i in -r..(r + 1) {
+            edge.push(origin.offset(Point::new(i, -r)));
+            edge.push(origin.offset(Point::new(i, r)));
+            edge.push(origin.offset(Point::new(-r, i)));
+            edge.push(origin.offset(Point::new(r, i)));
+        }
+
+        for target in edge.into_iter() {
+            for point in line(origin, target).into_iter().skip(1) {
+                let dx = point.x - origin.x;
+                let dy = point.y - origin.y;
+                if dx * dx + dy * dy > r * r || !self.size.in_bounds(point) {
+                    break;
+                }
+                self.mark_visible(point);
+                if terrain.tile_at(point).blocks_sight() {
+                    break;
+                }
+            }
+        }
+    }
+
+    pub fn is_visible(&self, point: Point) -> bool {
+        self.size.cell_index(point).map_or(false, |cell| self.visible[cell])
+    }
+
+    pub fn is_explored(&self, point: Point) -> bool {
+        self.size.cell_index(point).map_or(false, |cell| self.explored[cell])
+    }
+
+    pub fn set_explored(&mut self, point: Point) {
+        if let Some(cell) = self.size.cell_index(point) {
+            self.explored[cell] = true;
+        }
+    }
+
+    fn mark_visible(&mut self, point: Point) {
+        if let Some(cell) = self.size.cell_index(point) {
+            self.visible[cell] = true;
+            self.explored[cell] = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use terrain::Tile;
+
+    fn open_floor() -> Terrain {
+        Terrain::new(Bound::new(0, 0, 40, 30))
+    }
+
+    fn seen_from(origin: Point, terrain: &Terrain, radius: i32) -> FieldOfView {
+        let mut fov = FieldOfView::new(terrain.size, radius);
+        fov.compute(origin, terrain);
+        fov
+    }
+
+    #[test]
+    fn sees_as_far_as_the_radius() {
+        let terrain = open_floor();
+        let fov = seen_from(Point::new(20, 15), &terrain, 5);
+        assert!(fov.is_visible(Point::new(20, 15)));
+        assert!(fov.is_visible(Point::new(25, 15)));
+        assert!(fov.is_visible(Point::new(23, 19)));
+        assert!(!fov.is_visible(Point::new(26, 15)));
+        assert!(!fov.is_visible(Point::new(24, 19)));
+
+        let fov = seen_from(Point::new(20, 15), &terrain, 8);
+        assert!(fov.is_visible(Point::new(28, 15)));
+    }
+
+    #[test]
+    fn walls_block_sight() {
+        let mut terrain = open_floor();
+        for y in 0..30 {
+            terrain.set_tile(Point::new(22, y), Tile::Wall);
+        }
+        let fov = seen_from(Point::new(20, 15), &terrain, 8);
+        // The wall itself is seen, but nothing behind it
+        assert!(fov.is_visible(Point::new(21, 15)));
+        assert!(fov.is_visible(Point::new(22, 15)));
+        assert!(!fov.is_visible(Point::new(23, 15)));
+        assert!(!fov.is_visible(Point::new(25, 12)));
+        assert!(fov.is_visible(Point::new(14, 15)));
+    }
+
+    #[test]
+    fn remembers_what_was_seen() {
+        let terrain = open_floor();
+        let mut fov = seen_from(Point::new(5, 5), &terrain, 3);
+        fov.compute(Point::new(30, 20), &terrain);
+        assert!(!fov.is_visible(Point::new(5, 5)));
+        assert!(fov.is_explored(Point::new(5, 5)));
+        assert!(!fov.is_explored(Point::new(15, 15)));
+        assert!(!fov.is_visible(Point::new(-1, 5)));
+        assert!(!fov.is_explored(Point::new(40, 5)));
+    }
+}
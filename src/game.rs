@@ -1,45 +1,93 @@
 extern crate core;
+extern crate rand;
 
 use util::{Bound, Point};
-use rendering::renderers::{RenderingComponent, TcodRenderingComponent};
+use rendering::renderers::RenderingComponent;
+#[cfg(feature = "tcod")]
+use rendering::renderers::TcodRenderingComponent;
 use rendering::windows::{
   Windows,
   WindowComponent,
-  TcodStatsWindowComponent,
-  TcodInputWindowComponent,
-  TcodMessagesWindowComponent,
-  TcodMapWindowComponent
+  StatsWindowComponent,
+  InputWindowComponent,
+  MessagesWindowComponent,
+  MapWindowComponent
 };
-use actor::Actor;
 use input::KeyboardInput;
-use input::GameKey::Printable;
 use map::Maps;
-use game_states::{GameState, MovementGameState, AttackInputGameState};
+use fov::DEFAULT_FOV_RADIUS;
+use actor::Actor;
+use rng::GameRng;
+use dungeon;
+use monsters::MonsterRegistry;
+use terrain::{Terrain, Tile};
+use spatial::Occupancy;
+use save;
+use game_states::{
+  GameState,
+  Transition,
+  MovementGameState,
+  GameOverGameState,
+  TitleGameState,
+  OptionsGameState
+};
 
 use std::cell::RefCell;
+use std::cmp;
+use std::collections::BTreeMap;
+use std::mem;
+use std::path::Path;
 use std::rc::Rc;
 
-use combat::{
-  Boomerang,
-  Sword,
-};
+use item::{ItemRegistry, Slot};
 
 use self::core::ops::{Deref, DerefMut};
+use self::rand::Rng;
+
+// What the heroine sets out with, from the item definitions
+const STARTING_WEAPON: &'static str = "Heroic Sworc";
+
+// Settings picked from the options menu. They last as long as the program.
+#[derive(Copy, Clone, PartialEq, Default)]
+pub struct Options {
+  // Save every time the heroine takes the stairs
+  pub autosave: bool
+}
+
+impl Options {
+  pub fn new() -> Options {
+    Options { autosave: false }
+  }
+}
 
 pub struct MoveInfo {
   pub last_keypress: Option<KeyboardInput>,
   pub char_location: Point,
-  pub bounds: Bound
+  pub bounds: Bound,
+  pub rng: GameRng,
+  pub terrain: Rc<RefCell<Terrain>>,
+  pub occupancy: Rc<RefCell<Occupancy>>
 }
 
 impl MoveInfo {
-  pub fn new(bound: Bound) -> MoveInfo {
+  pub fn new(bound: Bound, rng: GameRng) -> MoveInfo {
     MoveInfo {
       last_keypress: None,
       char_location: Point::new(40, 25),
-      bounds: bound
+      bounds: bound,
+      rng: rng,
+      terrain: Rc::new(RefCell::new(Terrain::new(bound))),
+      occupancy: Rc::new(RefCell::new(Occupancy::new(bound)))
     }
   }
+
+  pub fn is_occupied(&self, point: Point) -> bool {
+    self.occupancy.borrow().is_occupied(point)
+  }
+
+  pub fn tile_at(&self, point: Point) -> Tile {
+    self.terrain.borrow().tile_at(point)
+  }
 }
 
 pub struct Game {
@@ -47,26 +95,97 @@ pub struct Game {
     pub window_bounds: Bound,
     pub rendering_component: Box<RenderingComponent>,
     pub windows: Windows,
-    pub game_state: Box<GameState>,
+    // Movement at the bottom, with any prompts opened over it on top
+    pub states: Vec<Box<GameState>>,
     pub maps: Maps,
+    // Levels the heroine has been to and left, by depth
+    pub other_levels: BTreeMap<u32, Maps>,
+    pub monsters: MonsterRegistry,
+    pub items: ItemRegistry,
+    pub input_log: Vec<KeyboardInput>,
+    pub save_path: String,
+    pub game_over: bool,
+    pub options: Options,
+    // How far the heroine sees, on every level
+    fov_radius: i32,
     move_info: Rc<RefCell<MoveInfo>>
 }
 
 impl Game {
+  #[cfg(feature = "tcod")]
   pub fn new() -> Game {
-    let total_bounds = Bound::new(0, 0, 99, 61);
+    Game::new_with_seed(GameRng::random_seed())
+  }
+
+  #[cfg(feature = "tcod")]
+  pub fn new_with_seed(seed: u32) -> Game {
+    let rc: Box<RenderingComponent> = Box::new(TcodRenderingComponent::new(Game::total_bounds()));
+    Game::new_with_renderer(rc, seed)
+  }
+
+  pub fn total_bounds() -> Bound {
+    Bound::new(0, 0, 99, 61)
+  }
+
+  pub fn new_with_renderer(rc: Box<RenderingComponent>, seed: u32) -> Game {
+    let mut game = Game::new_without_actors(rc, seed);
+    game.start_run();
+    game
+  }
+
+  // Waits on the title screen, the run starts once New game is picked
+  pub fn new_at_title(rc: Box<RenderingComponent>, seed: u32) -> Game {
+    let mut game = Game::new_without_actors(rc, seed);
+    game.states = vec![];
+    game.push_state(Box::new(TitleGameState::new()));
+    game
+  }
+
+  // Digs the first level and puts the heroine in it
+  fn start_run(&mut self) {
+    let move_info = self.move_info();
+    dungeon::generate_level(&mut self.maps, move_info.clone(), &self.monsters, &self.items);
+    let start = {
+      move_info.borrow().deref().char_location
+    };
+    let mut heroine = Actor::heroine(move_info);
+    if let Some(weapon) = self.items.create(STARTING_WEAPON) {
+      heroine.equipment.equip(Slot::MainHand, weapon);
+    }
+    self.maps.pcs.push_actor(start, Box::new(heroine));
+    self.refresh_stats();
+  }
+
+  // Throws away the run in progress and every state on the stack, leaving
+  // empty maps to start or load another run into
+  pub fn reset(&mut self, seed: u32) {
+    while let Some(state) = self.states.pop() {
+      state.exit(&mut self.windows);
+    }
+    let bounds = {
+      self.move_info.borrow().deref().bounds
+    };
+    self.move_info = Rc::new(RefCell::new(MoveInfo::new(bounds, GameRng::new(seed))));
+    self.maps = Maps::new(self.move_info.clone(), self.fov_radius);
+    self.other_levels.clear();
+    self.game_over = false;
+    self.windows.messages.flush_buffer();
+  }
+
+  // Windows, state and empty maps; used as the starting point when loading
+  pub fn new_without_actors(rc: Box<RenderingComponent>, seed: u32) -> Game {
+    let total_bounds = Game::total_bounds();
     let stats_bounds = Bound::new(79, 0, 99, 49);
     let input_bounds = Bound::new(0, 50, 99, 52);
     let message_bounds = Bound::new(0, 53, 99, 61);
     let map_bounds = Bound::new(0, 0, 78, 49);
 
-    let rc: Box<RenderingComponent> = Box::new(TcodRenderingComponent::new(total_bounds));
-    let sw: Box<WindowComponent> = Box::new(TcodStatsWindowComponent::new(stats_bounds));
-    let iw: Box<WindowComponent> = Box::new(TcodInputWindowComponent::new(input_bounds));
-    let mw: Box<WindowComponent> = Box::new(TcodMessagesWindowComponent::new(message_bounds));
-    let maw: Box<WindowComponent> = Box::new(TcodMapWindowComponent::new(map_bounds));
+    let sw: Box<WindowComponent> = Box::new(StatsWindowComponent::new(stats_bounds));
+    let iw: Box<WindowComponent> = Box::new(InputWindowComponent::new(input_bounds));
+    let mw: Box<WindowComponent> = Box::new(MessagesWindowComponent::new(message_bounds));
+    let maw: Box<WindowComponent> = Box::new(MapWindowComponent::new(map_bounds));
 
-    let windows = Windows {
+    let mut windows = Windows {
       input: iw,
       messages: mw,
       map: maw,
@@ -75,85 +194,275 @@ impl Game {
 
     let gs: Box<GameState> = Box::new(MovementGameState::new());
 
-    let move_info = Rc::new(RefCell::new(MoveInfo::new(map_bounds)));
-    let mut maps = Maps::new(move_info.clone());
-
-    maps.friends.push_actor(Point::new(10, 10), Box::new(Actor::dog(10, 10, move_info.clone())));
-    maps.friends.push_actor(Point::new(40, 25), Box::new(Actor::cat(40, 25, move_info.clone())));
-    maps.enemies.push_actor(Point::new(20, 20), Box::new(Actor::kobold(20, 20, move_info.clone())));
+    let move_info = Rc::new(RefCell::new(MoveInfo::new(map_bounds, GameRng::new(seed))));
+    let maps = Maps::new(move_info.clone(), DEFAULT_FOV_RADIUS);
 
-    let point = {
-        move_info.borrow().deref().char_location
-    };
-    maps.pcs.push_actor(point, Box::new(Actor::heroine(move_info.clone())));
+    let mut problems = vec![];
+    let monsters = MonsterRegistry::load(&mut problems);
+    let items = ItemRegistry::load(&mut problems);
+    for problem in problems.iter() {
+      windows.messages.buffer_message(problem);
+    }
 
     Game {
       exit: false,
       window_bounds: total_bounds,
       rendering_component: rc,
       windows: windows,
-      game_state: gs,
+      states: vec![gs],
       maps: maps,
+      other_levels: BTreeMap::new(),
+      monsters: monsters,
+      items: items,
+      input_log: vec![],
+      save_path: String::from(save::DEFAULT_SAVE_PATH),
+      game_over: false,
+      options: Options::new(),
+      fov_radius: DEFAULT_FOV_RADIUS,
       move_info: move_info
     }
   }
 
+  pub fn fov_radius(&self) -> i32 {
+    self.fov_radius
+  }
+
+  pub fn set_fov_radius(&mut self, radius: i32) {
+    self.fov_radius = radius;
+    self.maps.fov.radius = radius;
+    for level in self.other_levels.values_mut() {
+      level.fov.radius = radius;
+    }
+    self.maps.update_fov();
+  }
+
+  pub fn move_info(&self) -> Rc<RefCell<MoveInfo>> {
+    self.move_info.clone()
+  }
+
+  pub fn run(&mut self) {
+    // render
+    self.render();
+
+    // our game loop
+    while !(self.rendering_component.window_closed() || self.exit) {
+      // wait for user input
+      self.wait_for_keypress();
+      self.update();
+
+      self.render();
+    }
+  }
+
   pub fn render(&mut self) {
-    self.game_state.render(&mut self.rendering_component, &mut self.maps, &mut self.windows);
+    if let Some(state) = self.states.last_mut() {
+      state.render(&mut self.rendering_component, &mut self.maps, &mut self.windows);
+    }
   }
 
+  // Hands the key to the state on top and does whatever it asks for
   pub fn update(&mut self) {
-    if self.game_state.should_update_state() {
-      self.game_state.exit(&mut self.windows);
-      self.update_state();
-      self.game_state.enter(&mut self.windows);
+    let transition = match self.states.last_mut() {
+      Some(state) => state.update(&mut self.maps, &mut self.windows, self.move_info.clone()),
+      None => Transition::Stay
+    };
+    self.apply(transition);
+
+    if !self.game_over && self.maps.player_is_dead() {
+      self.game_over = true;
+      self.windows.messages.buffer_message("You die...");
+      while let Some(state) = self.states.pop() {
+        state.exit(&mut self.windows);
+      }
+      self.push_state(Box::new(GameOverGameState::new()));
     }
 
-    self.game_state.update(&mut self.maps, &mut self.windows, self.move_info.clone());
+    self.refresh_stats();
   }
 
-  pub fn wait_for_keypress(&mut self) -> KeyboardInput {
-    let k = self.rendering_component.wait_for_keypress();
-    {
-      self.move_info.borrow_mut().deref_mut().last_keypress = Some(k);
+  pub fn apply(&mut self, transition: Transition) {
+    match transition {
+      Transition::Stay => {},
+      Transition::Push(state) => self.push_state(state),
+      Transition::Pop => {
+        // The bottom of the stack is never popped
+        if self.states.len() > 1 {
+          if let Some(state) = self.states.pop() {
+            state.exit(&mut self.windows);
+          }
+          if let Some(state) = self.states.last() {
+            state.enter(&mut self.windows);
+          }
+        }
+      },
+      Transition::Save => { self.save(); },
+      Transition::TakeStairs(stairs) => self.take_stairs(stairs),
+      Transition::NewGame => {
+        let seed = self.seed();
+        self.reset(seed);
+        self.start_run();
+        self.push_state(Box::new(MovementGameState::new()));
+      },
+      Transition::Continue => {
+        let path = self.save_path.clone();
+        if !Path::new(&path).exists() {
+          self.windows.messages.buffer_message("There is no saved game to continue.");
+          return;
+        }
+        if let Err(e) = save::load_into(self, Path::new(&path)) {
+          self.return_to_title();
+          self.windows.messages.buffer_message(&e);
+        }
+      },
+      Transition::OpenOptions => self.push_state(Box::new(OptionsGameState::new_with_options(self.options))),
+      Transition::SetOptions(options) => self.options = options,
+      Transition::SaveAndQuit => {
+        // Close the pause menu first so the run is saved as it is played
+        self.apply(Transition::Pop);
+        if self.save() {
+          self.exit = true;
+        }
+      },
+      Transition::Abandon => {
+        self.return_to_title();
+        self.windows.messages.buffer_message("You abandon the run.");
+      },
+      Transition::ReturnToTitle => self.return_to_title(),
+      Transition::Quit => self.exit = true
     }
-    return k;
   }
 
-  fn update_state(&mut self) {
-    let last_keypress = {
-      self.move_info.borrow().deref().last_keypress
+  // The next run gets a seed drawn from this one, so a replay of the whole
+  // session still plays out the same
+  fn return_to_title(&mut self) {
+    let seed = {
+      self.move_info.borrow_mut().deref_mut().rng.gen()
+    };
+    self.reset(seed);
+    self.push_state(Box::new(TitleGameState::new()));
+    self.refresh_stats();
+  }
+
+  pub fn push_state(&mut self, state: Box<GameState>) {
+    state.enter(&mut self.windows);
+    self.states.push(state);
+  }
+
+  pub fn refresh_stats(&mut self) {
+    let mut stats = vec![format!("Seed: {}", self.seed()), format!("Depth: {}", self.maps.depth)];
+    match self.maps.player() {
+      Some(pc) => {
+        stats.push(format!("HP: {}", cmp::max(pc.health, 0)));
+        let wielding = pc.weapon().map(|weapon| weapon.get_name()).unwrap_or(String::from("nothing"));
+        stats.push(format!("Wielding: {}", wielding));
+        for (index, slot) in Slot::all().iter().enumerate() {
+          let item_name = pc.equipment.get(*slot).map(|item| item.name.clone()).unwrap_or(String::from("-"));
+          stats.push(format!("{}) {}: {}", index + 1, slot.label(), item_name));
+        }
+        stats.push(format!("Pack: {}/{}", pc.inventory.len(), pc.inventory.capacity));
+        for (index, item) in pc.inventory.items().iter().enumerate() {
+          stats.push(format!("{}) {}", index + 1, item.name));
+        }
+      },
+      None => {}
+    }
+    *self.windows.stats.get_mut_messages() = stats.into_iter().map(Box::new).collect();
+  }
+
+  // Moves the heroine to the next level along the staircase she is standing
+  // on. The level she leaves is kept as it is for when she comes back.
+  pub fn take_stairs(&mut self, stairs: Tile) {
+    let char_location = {
+      self.move_info.borrow().deref().char_location
+    };
+    if self.maps.terrain.borrow().tile_at(char_location) != stairs {
+      let way = if stairs == Tile::StairsDown { "down" } else { "up" };
+      self.windows.messages.buffer_message(&format!("There is no way {} here.", way));
+      return;
+    }
+
+    let heroine = match self.maps.pcs.id_at(char_location).and_then(|id| self.maps.pcs.remove_actor(id)) {
+      Some(heroine) => heroine,
+      None => { return; }
     };
-    match last_keypress {
-      Some(ks) => {
-        match ks.key {
-          Printable('/') => {
-            let w = Box::new(Sword::new());
-            let is: Box<AttackInputGameState> = Box::new(AttackInputGameState::new_with_weapon(w));
-            self.game_state = is as Box<GameState>;
-          },
-          Printable('^') => {
-            let w = Box::new(Boomerang::new());
-            let is: Box<AttackInputGameState> = Box::new(AttackInputGameState::new_with_weapon(w));
-            self.game_state = is as Box<GameState>;
-          },
-          Printable('*') => {
-            let w = Box::new(Boomerang::new());
-            let is: Box<AttackInputGameState> = Box::new(AttackInputGameState::new_with_weapon(w));
-            self.game_state = is as Box<GameState>;
-          },
-          Printable('%') => {
-            let w = Box::new(Boomerang::new());
-            let is: Box<AttackInputGameState> = Box::new(AttackInputGameState::new_with_weapon(w));
-            self.game_state = is as Box<GameState>;
-          },
-          _ => {
-            let ms: Box<GameState> = Box::new(MovementGameState::new());
-            self.game_state = ms;
+
+    let depth = if stairs == Tile::StairsDown { self.maps.depth + 1 } else { self.maps.depth - 1 };
+    let next = match self.other_levels.remove(&depth) {
+      Some(level) => {
+        level.make_current();
+        level
+      },
+      None => {
+        let mut level = Maps::new_level(self.move_info.clone(), depth, self.fov_radius);
+        dungeon::generate_level(&mut level, self.move_info.clone(), &self.monsters, &self.items);
+        level
+      }
+    };
+    let previous = mem::replace(&mut self.maps, next);
+    self.other_levels.insert(previous.depth, previous);
+
+    // Arrive on the other end of the staircase, or as close as possible if
+    // something is standing on it
+    let arrival_stairs = if stairs == Tile::StairsDown { Tile::StairsUp } else { Tile::StairsDown };
+    let stairs_point = self.maps.terrain.borrow().find_tile(arrival_stairs).unwrap_or(char_location);
+    let arrival = self.free_point_near(stairs_point);
+    {
+      self.move_info.borrow_mut().deref_mut().char_location = arrival;
+    }
+    self.maps.pcs.push_actor(arrival, heroine);
+
+    if stairs == Tile::StairsDown {
+      self.windows.messages.buffer_message(&format!("You descend to depth {}.", depth));
+    } else {
+      self.windows.messages.buffer_message(&format!("You climb back up to depth {}.", depth));
+    }
+    if self.options.autosave {
+      self.save();
+    }
+    self.refresh_stats();
+  }
+
+  fn free_point_near(&self, point: Point) -> Point {
+    let move_info = self.move_info.borrow();
+    let move_info = move_info.deref();
+    for radius in 0..cmp::max(move_info.bounds.max.x, move_info.bounds.max.y) {
+      for dx in -radius..(radius + 1) {
+        for dy in -radius..(radius + 1) {
+          let candidate = Point::new(point.x + dx, point.y + dy);
+          if !move_info.tile_at(candidate).blocks_movement() && !move_info.is_occupied(candidate) {
+            return candidate;
           }
         }
+      }
+    }
+    point
+  }
+
+  // Whether it worked, the message window says what happened either way
+  pub fn save(&mut self) -> bool {
+    let path = self.save_path.clone();
+    match save::save_game(self, Path::new(&path)) {
+      Ok(_) => {
+        self.windows.messages.buffer_message(&format!("Game saved to {}.", path));
+        true
       },
-      _ => {}
+      Err(e) => {
+        self.windows.messages.buffer_message(&e);
+        false
+      }
     }
   }
+
+  pub fn seed(&self) -> u32 {
+    self.move_info.borrow().deref().rng.seed()
+  }
+
+  pub fn wait_for_keypress(&mut self) -> KeyboardInput {
+    let k = self.rendering_component.wait_for_keypress();
+    self.input_log.push(k);
+    {
+      self.move_info.borrow_mut().deref_mut().last_keypress = Some(k);
+    }
+    return k;
+  }
 }
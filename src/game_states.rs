@@ -4,23 +4,54 @@ use std::rc::Rc;
 use std::cell::RefCell;
 
 use rendering::windows::Windows;
-use rendering::renderers::RenderingComponent;
+use rendering::renderers::{RenderingComponent, Color};
 use map::Maps;
-use game::MoveInfo;
-use input::GameKey::{SpecialKey};
-use input::{GameKeyCode};
+use game::{MoveInfo, Options};
+use input::{GameKey, GameKeyCode, KeyboardInput};
+use input::GameKey::{SpecialKey, Printable};
 use util::Point;
-use combat::{Weapon, Boomerang};
+use combat::{Attack, Weapon, Fists};
+use movement::{ATTACK_COST, ITEM_COST};
+use pathfinding::distance;
+use item::Slot;
+use terrain::Tile;
 
 use self::core::ops::Deref;
 
+// What the game should do once the state on top has handled a key
+pub enum Transition {
+  Stay,
+  // Open another state over this one, like a prompt
+  Push(Box<GameState>),
+  // Close this state and go back to the one underneath, as it was
+  Pop,
+  // Things only the game as a whole can do. None of them is a turn.
+  Save,
+  TakeStairs(Tile),
+  // From the menus
+  NewGame,
+  Continue,
+  OpenOptions,
+  SetOptions(Options),
+  SaveAndQuit,
+  // Back to the title screen without saving
+  Abandon,
+  // Back to the title screen once the run is over
+  ReturnToTitle,
+  Quit
+}
+
+// The game keeps a stack of these; only the one on top gets the keys
 pub trait GameState {
+  // Called when the state comes out on top, whether it was just pushed or
+  // the one above it was popped
   fn enter(&self, &mut Windows) {}
   fn exit(&self, &mut Windows) {}
 
-  fn should_update_state(&self) -> bool;
+  // How the state is written to a save file, see `save::load_game_state`
+  fn save_tag(&self) -> String;
 
-  fn update(&mut self, maps: &mut Maps, windows: &mut Windows, Rc<RefCell<MoveInfo>>);
+  fn update(&mut self, maps: &mut Maps, windows: &mut Windows, Rc<RefCell<MoveInfo>>) -> Transition;
 
   fn render(&mut self, renderer: &mut Box<RenderingComponent>, maps: &mut Maps, windows: &mut Windows) {
     renderer.before_render_new_frame();
@@ -28,8 +59,12 @@ pub trait GameState {
       renderer.attach_window(*window);
     }
     maps.render(renderer);
+    self.render_overlay(renderer);
     renderer.after_render_new_frame();
   }
+
+  // Drawn over the map, e.g. a cursor
+  fn render_overlay(&self, &mut Box<RenderingComponent>) {}
 }
 
 pub struct MovementGameState;
@@ -40,55 +75,79 @@ impl MovementGameState {
 }
 
 impl GameState for MovementGameState {
-  fn should_update_state(&self) -> bool {
-    true
+  fn save_tag(&self) -> String {
+    String::from("movement")
   }
 
-  fn update(&mut self, maps: &mut Maps, windows: &mut Windows, move_info: Rc<RefCell<MoveInfo>>) {
-    let last_keypress = {
-      move_info.borrow().deref().last_keypress
+  fn update(&mut self, maps: &mut Maps, windows: &mut Windows, move_info: Rc<RefCell<MoveInfo>>) -> Transition {
+    let (last_keypress, char_location) = {
+      let move_info = move_info.borrow();
+      (move_info.deref().last_keypress, move_info.deref().char_location)
     };
-    match last_keypress {
-      Some(ks) => {
-        match ks.key {
-          // Because Shift is used for attack keys we don't want to do
-          // anything when it's pushed. We can check for shift when we
-          // process the next keypress
-          SpecialKey(GameKeyCode::Shift) => {},
-          _ => {
-            maps.update(windows);
-          }
+    let ks = match last_keypress {
+      Some(ks) => ks,
+      None => { return Transition::Stay; }
+    };
+    match ks.key {
+      // Because Shift is used for attack keys we don't want to do
+      // anything when it's pushed. We can check for shift when we
+      // process the next keypress
+      SpecialKey(GameKeyCode::Shift) => Transition::Stay,
+      SpecialKey(GameKeyCode::Escape) => Transition::Push(Box::new(PauseGameState::new())),
+      Printable('S') => Transition::Save,
+      Printable('>') => Transition::TakeStairs(Tile::StairsDown),
+      Printable('<') => Transition::TakeStairs(Tile::StairsUp),
+      // Attacks are made with whatever is wielded
+      Printable('a') => {
+        let weapon = match maps.player().and_then(|pc| pc.weapon()) {
+          Some(weapon) => weapon.box_clone(),
+          None => Box::new(Fists::new())
+        };
+        if weapon.is_thrown() {
+          Transition::Push(Box::new(ThrowGameState::new_with_target(char_location)))
+        } else {
+          Transition::Push(Box::new(AttackInputGameState::new_with_weapon(weapon)))
         }
       },
-      _ => {}
+      Printable('f') => Transition::Push(Box::new(FeedGameState::new())),
+      Printable('d') => Transition::Push(Box::new(InventoryGameState::new_with_action(InventoryAction::Drop))),
+      Printable('w') => Transition::Push(Box::new(InventoryGameState::new_with_action(InventoryAction::Wield))),
+      Printable('u') => Transition::Push(Box::new(InventoryGameState::new_with_action(InventoryAction::Unwield))),
+      Printable('g') => {
+        if maps.pick_up(windows) {
+          maps.spend_turn(ITEM_COST, windows);
+        }
+        Transition::Stay
+      },
+      _ => {
+        maps.update(windows);
+        Transition::Stay
+      }
     }
   }
 }
 
 pub struct AttackInputGameState {
-  should_update_state: bool,
   pub weapon: Box<Weapon>
 }
 impl AttackInputGameState {
   pub fn new() -> AttackInputGameState {
-    let weapon = Box::new(Boomerang::new());
+    let weapon = Box::new(Fists::new());
     AttackInputGameState {
-      should_update_state: false,
       weapon: weapon
     }
   }
 
   pub fn new_with_weapon(weapon: Box<Weapon + 'static>) -> AttackInputGameState {
     AttackInputGameState {
-        should_update_state: false,
         weapon: weapon
     }
   }
 }
 
 impl GameState for AttackInputGameState {
-  fn should_update_state(&self) -> bool {
-    self.should_update_state
+  fn save_tag(&self) -> String {
+    format!("attack_input {}", self.weapon.get_name())
   }
 
   fn enter(&self, windows: &mut Windows) {
@@ -99,69 +158,437 @@ impl GameState for AttackInputGameState {
     windows.input.buffer_message(&msg);
   }
 
-  fn update(&mut self, maps: &mut Maps, windows: &mut Windows, move_info: Rc<RefCell<MoveInfo>>) {
+  fn update(&mut self, maps: &mut Maps, windows: &mut Windows, move_info: Rc<RefCell<MoveInfo>>) -> Transition {
     let last_keypress = {
       move_info.borrow().deref().last_keypress
     };
     match last_keypress {
       Some(ks) => {
-        let mut msg = "You attack ".to_string();
         let char_point = {
           move_info.borrow().deref().char_location.clone()
         };
-        let mut point = Point::new(0, 0);
-        match ks.key {
-          SpecialKey(GameKeyCode::Up) => {
-            point = char_point.offset_y(-1);
-            msg.push_str("up");
-            self.should_update_state = true;
+        let point = match ks.key {
+          SpecialKey(GameKeyCode::Up) => char_point.offset_y(-1),
+          SpecialKey(GameKeyCode::Down) => char_point.offset_y(1),
+          SpecialKey(GameKeyCode::Left) => char_point.offset_x(-1),
+          SpecialKey(GameKeyCode::Right) => char_point.offset_x(1),
+          SpecialKey(GameKeyCode::Escape) => {
+            windows.messages.buffer_message("Never mind.");
+            return Transition::Pop;
           },
-          SpecialKey(GameKeyCode::Down) => {
-            point = char_point.offset_y(1);
-            msg.push_str("down");
-            self.should_update_state = true;
-          },
-          SpecialKey(GameKeyCode::Left) => {
-            point = char_point.offset_x(-1);
-            msg.push_str("left");
-            self.should_update_state = true;
+          _ => { return Transition::Stay; }
+        };
+
+        if self.weapon.is_ranged() {
+          let direction = Point::new(point.x - char_point.x, point.y - char_point.y);
+          maps.throw_boomerang(direction, windows);
+        } else {
+          // Swinging at nothing takes no time
+          if maps.enemy_at(point).is_none() {
+            windows.messages.buffer_message("No enemy in that direction!");
+            return Transition::Pop;
+          }
+          // Settled the same way as walking into the enemy
+          let attack = match maps.player() {
+            Some(pc) => Attack { attacker: pc.name.clone(), stats: pc.stats, target: point },
+            None => { return Transition::Pop; }
+          };
+          maps.resolve_pc_attacks(vec![attack], windows);
+          maps.spend_turn(ATTACK_COST, windows);
+        }
+        Transition::Pop
+      },
+      _ => Transition::Stay
+    }
+  }
+
+  fn exit(&self, windows: &mut Windows) {
+    windows.input.flush_buffer();
+  }
+}
+
+// Asks which neighbour to hand the lettuce to
+#[derive(Default)]
+pub struct FeedGameState;
+impl FeedGameState {
+  pub fn new() -> FeedGameState {
+    FeedGameState
+  }
+}
+
+impl GameState for FeedGameState {
+  fn save_tag(&self) -> String {
+    String::from("feed")
+  }
+
+  fn enter(&self, windows: &mut Windows) {
+    windows.input.flush_buffer();
+    windows.input.buffer_message("Which direction do you want to feed? [Use the arrow keys to answer]");
+  }
+
+  fn update(&mut self, maps: &mut Maps, windows: &mut Windows, move_info: Rc<RefCell<MoveInfo>>) -> Transition {
+    let (last_keypress, char_point) = {
+      let move_info = move_info.borrow();
+      (move_info.deref().last_keypress, move_info.deref().char_location)
+    };
+    let target = match last_keypress {
+      Some(ks) => {
+        match ks.key {
+          SpecialKey(GameKeyCode::Up) => char_point.offset_y(-1),
+          SpecialKey(GameKeyCode::Down) => char_point.offset_y(1),
+          SpecialKey(GameKeyCode::Left) => char_point.offset_x(-1),
+          SpecialKey(GameKeyCode::Right) => char_point.offset_x(1),
+          SpecialKey(GameKeyCode::Escape) => {
+            windows.messages.buffer_message("Never mind.");
+            return Transition::Pop;
           },
-          SpecialKey(GameKeyCode::Right) => {
-            point = char_point.offset_x(1);
-            msg.push_str("right");
-            self.should_update_state = true;
+          _ => { return Transition::Stay; }
+        }
+      },
+      None => { return Transition::Stay; }
+    };
+    maps.feed(target, windows);
+    Transition::Pop
+  }
+
+  fn exit(&self, windows: &mut Windows) {
+    windows.input.flush_buffer();
+  }
+}
+
+// Picks the tile to throw the wielded weapon at: the arrow keys move the
+// target around the heroine, `a` throws and anything else gives up
+pub struct ThrowGameState {
+  pub target: Point
+}
+impl ThrowGameState {
+  pub fn new_with_target(target: Point) -> ThrowGameState {
+    ThrowGameState {
+      target: target
+    }
+  }
+}
+
+impl GameState for ThrowGameState {
+  fn save_tag(&self) -> String {
+    format!("throw {} {}", self.target.x, self.target.y)
+  }
+
+  fn enter(&self, windows: &mut Windows) {
+    windows.input.flush_buffer();
+    windows.input.buffer_message("Where do you want to throw it? [Arrow keys to aim, a to throw]");
+  }
+
+  fn update(&mut self, maps: &mut Maps, windows: &mut Windows, move_info: Rc<RefCell<MoveInfo>>) -> Transition {
+    let (last_keypress, char_point) = {
+      let move_info = move_info.borrow();
+      (move_info.deref().last_keypress, move_info.deref().char_location)
+    };
+    let target = match last_keypress {
+      Some(ks) => {
+        match ks.key {
+          SpecialKey(GameKeyCode::Up) => self.target.offset_y(-1),
+          SpecialKey(GameKeyCode::Down) => self.target.offset_y(1),
+          SpecialKey(GameKeyCode::Left) => self.target.offset_x(-1),
+          SpecialKey(GameKeyCode::Right) => self.target.offset_x(1),
+          Printable('a') => {
+            maps.throw_bomb(self.target, windows);
+            return Transition::Pop;
           },
-          _ => {}
+          _ => {
+            windows.messages.buffer_message("Never mind.");
+            return Transition::Pop;
+          }
         }
+      },
+      None => { return Transition::Stay; }
+    };
+    let range = maps.player().and_then(|pc| pc.weapon()).map_or(1, |weapon| weapon.range());
+    if distance(char_point, target) <= range as u32 {
+      self.target = target;
+    }
+    Transition::Stay
+  }
+
+  fn render_overlay(&self, renderer: &mut Box<RenderingComponent>) {
+    renderer.render_object(self.target, 'X', Color::Red, Color::Black);
+  }
+
+  fn exit(&self, windows: &mut Windows) {
+    windows.input.flush_buffer();
+  }
+}
+
+// What to do with the item the player picks from the inventory prompt
+#[derive(Copy, Clone, PartialEq)]
+pub enum InventoryAction {
+  Drop,
+  Wield,
+  Unwield
+}
+
+impl InventoryAction {
+  pub fn name(&self) -> &'static str {
+    match *self {
+      InventoryAction::Drop => "drop",
+      InventoryAction::Wield => "wield",
+      InventoryAction::Unwield => "unwield"
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<InventoryAction> {
+    match name {
+      "drop" => Some(InventoryAction::Drop),
+      "wield" => Some(InventoryAction::Wield),
+      "unwield" => Some(InventoryAction::Unwield),
+      _ => None
+    }
+  }
+}
+
+// Asks which carried item (or, to unwield, which slot) the action is for.
+// Both are picked by their number in the stats window; any other key
+// changes her mind.
+pub struct InventoryGameState {
+  pub action: InventoryAction
+}
+impl InventoryGameState {
+  pub fn new_with_action(action: InventoryAction) -> InventoryGameState {
+    InventoryGameState {
+      action: action
+    }
+  }
+}
+
+impl GameState for InventoryGameState {
+  fn save_tag(&self) -> String {
+    format!("inventory {}", self.action.name())
+  }
 
-        if self.should_update_state {
-          match maps.enemy_at(point) {
-            Some(enemy) => {
-              // msg.push_str(" with your ");
-              // msg.push_str(&self.weapon);
-              // msg.push_str("!");
-              // windows.messages.buffer_message(&msg);
-
-              let pc = maps.pcs.actor_at(char_point).unwrap();
-              println!("{}", pc.display_char);
-              msg.push_str(" with your ");
-              msg.push_str(&self.weapon.get_name());
-              msg.push_str(" for ");
-              msg.push_str(&self.weapon.deal_damage(enemy).to_string());
-              msg.push_str(" points of damage!");
-              windows.messages.buffer_message(&msg);
-            },
-            None => {
-              windows.messages.buffer_message("No enemy in that direction!");
+  fn enter(&self, windows: &mut Windows) {
+    windows.input.flush_buffer();
+    let msg = match self.action {
+      InventoryAction::Unwield => String::from("Which slot do you want to empty? [Press its number]"),
+      action => format!("Which item do you want to {}? [Press its number]", action.name())
+    };
+    windows.input.buffer_message(&msg);
+  }
+
+  fn update(&mut self, maps: &mut Maps, windows: &mut Windows, move_info: Rc<RefCell<MoveInfo>>) -> Transition {
+    let last_keypress = {
+      move_info.borrow().deref().last_keypress
+    };
+    match last_keypress {
+      Some(ks) => {
+        match ks.key {
+          Printable(c) if c >= '1' && c <= '9' => {
+            let index = c as usize - '1' as usize;
+            let done = match self.action {
+              InventoryAction::Drop => maps.drop_item(index, windows),
+              InventoryAction::Wield => maps.wield(index, windows),
+              InventoryAction::Unwield => {
+                match Slot::all().get(index) {
+                  Some(slot) => maps.unwield(*slot, windows),
+                  None => {
+                    windows.messages.buffer_message("You don't have that.");
+                    false
+                  }
+                }
+              }
+            };
+            if done {
+              maps.spend_turn(ITEM_COST, windows);
             }
-          }
+          },
+          _ => windows.messages.buffer_message("Never mind.")
         }
+        Transition::Pop
       },
-      _ => {},
+      None => Transition::Stay
     }
   }
 
   fn exit(&self, windows: &mut Windows) {
     windows.input.flush_buffer();
   }
-}
\ No newline at end of file
+}
+
+// The heroine is dead; nothing moves any more until the player goes back to
+// the title screen
+#[derive(Default)]
+pub struct GameOverGameState;
+impl GameOverGameState {
+  pub fn new() -> GameOverGameState {
+    GameOverGameState
+  }
+}
+
+impl GameState for GameOverGameState {
+  fn save_tag(&self) -> String {
+    String::from("game_over")
+  }
+
+  fn enter(&self, windows: &mut Windows) {
+    windows.input.flush_buffer();
+    windows.input.buffer_message("You have died. [Press Escape to return to the title screen]");
+  }
+
+  fn update(&mut self, _: &mut Maps, _: &mut Windows, move_info: Rc<RefCell<MoveInfo>>) -> Transition {
+    let last_keypress = {
+      move_info.borrow().deref().last_keypress
+    };
+    match last_keypress {
+      Some(KeyboardInput { key: SpecialKey(GameKeyCode::Escape) }) => Transition::ReturnToTitle,
+      _ => Transition::Stay
+    }
+  }
+}
+
+// Writes a menu into the map window, one line each, with a blank line under
+// the heading
+fn show_menu(windows: &mut Windows, heading: &str, entries: &[String]) {
+  let mut lines = vec![Box::new(String::from(heading)), Box::new(String::new())];
+  lines.extend(entries.iter().map(|entry| Box::new(entry.clone())));
+  *windows.map.get_mut_messages() = lines;
+}
+
+fn render_windows(renderer: &mut Box<RenderingComponent>, windows: &mut Windows) {
+  renderer.before_render_new_frame();
+  for window in windows.all_windows().iter_mut() {
+    renderer.attach_window(*window);
+  }
+  renderer.after_render_new_frame();
+}
+
+fn menu_key(move_info: &Rc<RefCell<MoveInfo>>) -> Option<GameKey> {
+  move_info.borrow().deref().last_keypress.map(|ks| ks.key)
+}
+
+// What the game opens on, before there is a run to show
+#[derive(Default)]
+pub struct TitleGameState;
+impl TitleGameState {
+  pub fn new() -> TitleGameState {
+    TitleGameState
+  }
+}
+
+impl GameState for TitleGameState {
+  fn save_tag(&self) -> String {
+    String::from("title")
+  }
+
+  fn enter(&self, windows: &mut Windows) {
+    show_menu(windows, "roguelike", &[
+      String::from("n) New game"),
+      String::from("c) Continue"),
+      String::from("o) Options"),
+      String::from("q) Quit")
+    ]);
+    windows.input.flush_buffer();
+    windows.input.buffer_message("[Press a letter to choose]");
+  }
+
+  fn exit(&self, windows: &mut Windows) {
+    windows.map.flush_buffer();
+    windows.input.flush_buffer();
+  }
+
+  fn update(&mut self, _: &mut Maps, _: &mut Windows, move_info: Rc<RefCell<MoveInfo>>) -> Transition {
+    match menu_key(&move_info) {
+      Some(Printable('n')) => Transition::NewGame,
+      Some(Printable('c')) => Transition::Continue,
+      Some(Printable('o')) => Transition::OpenOptions,
+      Some(Printable('q')) | Some(SpecialKey(GameKeyCode::Escape)) => Transition::Quit,
+      _ => Transition::Stay
+    }
+  }
+
+  // There are no maps yet, only the windows
+  fn render(&mut self, renderer: &mut Box<RenderingComponent>, _: &mut Maps, windows: &mut Windows) {
+    render_windows(renderer, windows);
+  }
+}
+
+// Opened over the title screen. Every change is handed to the game as it is
+// made, so there is nothing to confirm on the way out.
+pub struct OptionsGameState {
+  pub options: Options
+}
+impl OptionsGameState {
+  pub fn new_with_options(options: Options) -> OptionsGameState {
+    OptionsGameState {
+      options: options
+    }
+  }
+}
+
+impl GameState for OptionsGameState {
+  fn save_tag(&self) -> String {
+    String::from("options")
+  }
+
+  fn enter(&self, windows: &mut Windows) {
+    let autosave = if self.options.autosave { "on" } else { "off" };
+    show_menu(windows, "Options", &[
+      format!("a) Autosave on the stairs: {}", autosave),
+      String::from("Escape) Back")
+    ]);
+    windows.input.flush_buffer();
+    windows.input.buffer_message("[Press a letter to change a setting]");
+  }
+
+  fn exit(&self, windows: &mut Windows) {
+    windows.map.flush_buffer();
+    windows.input.flush_buffer();
+  }
+
+  fn update(&mut self, _: &mut Maps, windows: &mut Windows, move_info: Rc<RefCell<MoveInfo>>) -> Transition {
+    match menu_key(&move_info) {
+      Some(Printable('a')) => {
+        self.options.autosave = !self.options.autosave;
+        self.enter(windows);
+        Transition::SetOptions(self.options)
+      },
+      Some(SpecialKey(GameKeyCode::Escape)) => Transition::Pop,
+      _ => Transition::Stay
+    }
+  }
+
+  fn render(&mut self, renderer: &mut Box<RenderingComponent>, _: &mut Maps, windows: &mut Windows) {
+    render_windows(renderer, windows);
+  }
+}
+
+// Escape during a run. Nothing moves while it is open.
+#[derive(Default)]
+pub struct PauseGameState;
+impl PauseGameState {
+  pub fn new() -> PauseGameState {
+    PauseGameState
+  }
+}
+
+impl GameState for PauseGameState {
+  fn save_tag(&self) -> String {
+    String::from("pause")
+  }
+
+  fn enter(&self, windows: &mut Windows) {
+    windows.input.flush_buffer();
+    windows.input.buffer_message("Paused. r) Resume  s) Save and quit  a) Abandon run");
+  }
+
+  fn exit(&self, windows: &mut Windows) {
+    windows.input.flush_buffer();
+  }
+
+  fn update(&mut self, _: &mut Maps, _: &mut Windows, move_info: Rc<RefCell<MoveInfo>>) -> Transition {
+    match menu_key(&move_info) {
+      Some(Printable('r')) | Some(SpecialKey(GameKeyCode::Escape)) => Transition::Pop,
+      Some(Printable('s')) => Transition::SaveAndQuit,
+      Some(Printable('a')) => Transition::Abandon,
+      _ => Transition::Stay
+    }
+  }
+}
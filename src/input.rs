@@ -1,20 +1,28 @@
+#[cfg(feature = "tcod")]
 extern crate tcod;
 
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+#[cfg(feature = "tcod")]
 use self::tcod::input::Key;
-use self::tcod::input::KeyCode::{Up, Down, Left, Right, Number6, Number8, Number5, Shift, Escape};
+#[cfg(feature = "tcod")]
+use self::tcod::input::KeyCode::{Up, Down, Left, Right, Number6, Number8, Number5, Shift, Escape, Char};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 pub enum GameKey {
     Printable(char),
     SpecialKey(GameKeyCode)
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 pub struct KeyboardInput {
   pub key: GameKey
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 pub enum GameKeyCode {
     // Arrow keys
     Up,
@@ -30,15 +38,131 @@ pub enum GameKeyCode {
     None
 }
 
+impl KeyboardInput {
+  pub fn new(key: GameKey) -> KeyboardInput {
+    KeyboardInput { key: key }
+  }
+
+  // Keys are spelled the way they appear in input scripts, e.g. `up`,
+  // `esc` or a single printable character such as `/`.
+  pub fn from_script_token(token: &str) -> Option<KeyboardInput> {
+    let key = match token {
+      "up" => GameKey::SpecialKey(GameKeyCode::Up),
+      "down" => GameKey::SpecialKey(GameKeyCode::Down),
+      "left" => GameKey::SpecialKey(GameKeyCode::Left),
+      "right" => GameKey::SpecialKey(GameKeyCode::Right),
+      "shift" => GameKey::SpecialKey(GameKeyCode::Shift),
+      "esc" | "escape" => GameKey::SpecialKey(GameKeyCode::Escape),
+      "none" => GameKey::SpecialKey(GameKeyCode::None),
+      "comma" => GameKey::Printable(','),
+      "space" => GameKey::Printable(' '),
+      "hash" => GameKey::Printable('#'),
+      _ => {
+        let mut chars = token.chars();
+        match (chars.next(), chars.next()) {
+          (Some(c), None) => GameKey::Printable(c),
+          _ => { return None; }
+        }
+      }
+    };
+    Some(KeyboardInput::new(key))
+  }
+
+  pub fn to_script_token(&self) -> String {
+    match self.key {
+      GameKey::SpecialKey(GameKeyCode::Up) => String::from("up"),
+      GameKey::SpecialKey(GameKeyCode::Down) => String::from("down"),
+      GameKey::SpecialKey(GameKeyCode::Left) => String::from("left"),
+      GameKey::SpecialKey(GameKeyCode::Right) => String::from("right"),
+      GameKey::SpecialKey(GameKeyCode::Shift) => String::from("shift"),
+      GameKey::SpecialKey(GameKeyCode::Escape) => String::from("esc"),
+      GameKey::SpecialKey(GameKeyCode::None) => String::from("none"),
+      GameKey::Printable(',') => String::from("comma"),
+      GameKey::Printable(' ') => String::from("space"),
+      GameKey::Printable('#') => String::from("hash"),
+      GameKey::Printable(c) => c.to_string()
+    }
+  }
+}
+
 pub trait InputComponent<T> {
   fn translate_input(&self, T) -> KeyboardInput;
 }
 
+// Where the game gets its keypresses from. Sources that can run out (scripts)
+// report it through `is_exhausted` so the game loop knows when to stop.
+pub trait InputSource {
+  fn next_keypress(&mut self) -> Option<KeyboardInput>;
+  fn is_exhausted(&self) -> bool;
+}
+
+pub struct ScriptedInputSource {
+  keypresses: VecDeque<KeyboardInput>
+}
+
+impl ScriptedInputSource {
+  pub fn new(keypresses: Vec<KeyboardInput>) -> ScriptedInputSource {
+    ScriptedInputSource { keypresses: keypresses.into_iter().collect() }
+  }
+
+  // Scripts are keys separated by commas or whitespace, e.g. `up,up,a,left,esc`.
+  // Everything after a `#` on a line is a comment.
+  pub fn from_script(script: &str) -> Result<ScriptedInputSource, String> {
+    let mut keypresses = vec![];
+    for line in script.lines() {
+      let line = match line.find('#') {
+        Some(index) => &line[..index],
+        None => line
+      };
+      for token in line.split(|c: char| c == ',' || c.is_whitespace()) {
+        if token.is_empty() {
+          continue;
+        }
+        match KeyboardInput::from_script_token(token) {
+          Some(keypress) => keypresses.push(keypress),
+          None => { return Err(format!("Unknown key '{}' in input script", token)); }
+        }
+      }
+    }
+    Ok(ScriptedInputSource::new(keypresses))
+  }
+
+  pub fn from_file(path: &Path) -> Result<ScriptedInputSource, String> {
+    let mut script = String::new();
+    match File::open(path).and_then(|mut f| f.read_to_string(&mut script)) {
+      Ok(_) => ScriptedInputSource::from_script(&script),
+      Err(e) => Err(format!("Could not read input script {}: {}", path.display(), e))
+    }
+  }
+
+  pub fn to_script(keypresses: &[KeyboardInput]) -> String {
+    let tokens: Vec<String> = keypresses.iter().map(|k| k.to_script_token()).collect();
+    tokens.join(",")
+  }
+
+  pub fn push_keypress(&mut self, keypress: KeyboardInput) {
+    self.keypresses.push_back(keypress);
+  }
+}
+
+impl InputSource for ScriptedInputSource {
+  fn next_keypress(&mut self) -> Option<KeyboardInput> {
+    self.keypresses.pop_front()
+  }
+
+  fn is_exhausted(&self) -> bool {
+    self.keypresses.is_empty()
+  }
+}
+
+#[cfg(feature = "tcod")]
 pub struct TcodInputComponent;
+#[cfg(feature = "tcod")]
 impl TcodInputComponent {
   pub fn new() -> TcodInputComponent { TcodInputComponent }
 }
 
+#[cfg(feature = "tcod")]
 impl InputComponent<Key> for TcodInputComponent {
   fn translate_input(&self, k: Key) -> KeyboardInput {
     let key: GameKey = if k.shift {
@@ -46,17 +170,23 @@ impl InputComponent<Key> for TcodInputComponent {
         Key { code: Number5, ..  } => GameKey::Printable('%'),
         Key { code: Number6, ..  } => GameKey::Printable('^'),
         Key { code: Number8, ..  } => GameKey::Printable('%'),
+        Key { printable: ',', .. } => GameKey::Printable('<'),
+        Key { printable: '.', .. } => GameKey::Printable('>'),
+        Key { code: Char, printable, .. } => GameKey::Printable(printable.to_ascii_uppercase()),
         _ => GameKey::SpecialKey(GameKeyCode::None)
       }
     } else {
       match k {
         Key { printable: '/', .. } => GameKey::Printable('/'),
+        Key { printable: '<', .. } => GameKey::Printable('<'),
+        Key { printable: '>', .. } => GameKey::Printable('>'),
         Key { code: Up, .. } => GameKey::SpecialKey(GameKeyCode::Up),
         Key { code: Down, .. } => GameKey::SpecialKey(GameKeyCode::Down),
         Key { code: Left, .. } => GameKey::SpecialKey(GameKeyCode::Left),
         Key { code: Right, .. } =>  GameKey::SpecialKey(GameKeyCode::Right),
         Key { code: Shift, ..  } => GameKey::SpecialKey(GameKeyCode::Shift),
         Key { code: Escape, ..  } => GameKey::SpecialKey(GameKeyCode::Escape),
+        Key { printable, .. } if printable.is_ascii_alphanumeric() => GameKey::Printable(printable),
         _ => GameKey::SpecialKey(GameKeyCode::None)
       }
     };
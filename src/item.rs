@@ -0,0 +1,401 @@
+use std::path::Path;
+
+use rendering::renderers::{RenderingComponent, Color};
+use combat::{Weapon, DefinedWeapon, Dice};
+use definitions::{Record, blocks, load_or_builtin};
+use util::Point;
+use fov::FieldOfView;
+
+pub const DEFAULT_CAPACITY: usize = 9;
+
+// Where on the body an item is worn or held
+#[derive(Copy, Clone, PartialEq)]
+pub enum Slot {
+    MainHand,
+    OffHand,
+    Armor
+}
+
+impl Slot {
+    pub fn all() -> [Slot; 3] {
+        [Slot::MainHand, Slot::OffHand, Slot::Armor]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Slot::MainHand => "main_hand",
+            Slot::OffHand => "off_hand",
+            Slot::Armor => "armor"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Slot> {
+        match name {
+            "main_hand" => Some(Slot::MainHand),
+            "off_hand" => Some(Slot::OffHand),
+            "armor" => Some(Slot::Armor),
+            _ => None
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match *self {
+            Slot::MainHand => "Main hand",
+            Slot::OffHand => "Off hand",
+            Slot::Armor => "Armor"
+        }
+    }
+}
+
+// What is special about an item: returning weapons come back to the
+// thrower, blast weapons go off where they land and food can be fed to
+// animals
+#[derive(Copy, Clone, PartialEq)]
+pub enum ItemEffect {
+    Returns,
+    Blast,
+    Feed
+}
+
+impl ItemEffect {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ItemEffect::Returns => "returns",
+            ItemEffect::Blast => "blast",
+            ItemEffect::Feed => "feed"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ItemEffect> {
+        match name {
+            "returns" => Some(ItemEffect::Returns),
+            "blast" => Some(ItemEffect::Blast),
+            "feed" => Some(ItemEffect::Feed),
+            _ => None
+        }
+    }
+}
+
+// Something that can lie on the floor or be carried around
+pub struct Item {
+    pub name: String,
+    pub display_char: char,
+    pub foreground: Color,
+    pub slot: Option<Slot>,
+    pub weapon: Option<Box<Weapon>>,
+    pub effect: Option<ItemEffect>,
+    pub heal: Option<Dice>,
+    // What it does when it goes off, for blast items
+    pub blast: Option<Dice>
+}
+
+impl Clone for Item {
+    fn clone(&self) -> Item {
+        Item {
+            name: self.name.clone(),
+            display_char: self.display_char,
+            foreground: self.foreground,
+            slot: self.slot,
+            weapon: self.weapon.as_ref().map(|weapon| weapon.box_clone()),
+            effect: self.effect,
+            heal: self.heal,
+            blast: self.blast
+        }
+    }
+}
+
+pub const ITEM_DEFINITIONS_PATH: &'static str = "data/items.txt";
+const ITEM_DEFINITIONS: &'static str = include_str!("../data/items.txt");
+
+// Nothing can be thrown further than across the whole map
+pub const MAX_RANGE: i32 = 78;
+
+pub struct ItemDef {
+    pub name: String,
+    pub display_char: char,
+    pub foreground: Color,
+    pub slot: Option<Slot>,
+    pub damage: Option<Dice>,
+    pub heal: Option<Dice>,
+    pub blast: Option<Dice>,
+    pub range: i32,
+    pub effect: Option<ItemEffect>
+}
+
+impl ItemDef {
+    fn new(name: &str) -> ItemDef {
+        ItemDef {
+            name: String::from(name),
+            display_char: '?',
+            foreground: Color::White,
+            slot: None,
+            damage: None,
+            heal: None,
+            blast: None,
+            range: 1,
+            effect: None
+        }
+    }
+
+    fn read(&mut self, record: &Record) -> Result<(), String> {
+        match record.name {
+            "glyph" => self.display_char = record.char()?,
+            "color" => self.foreground = record.named(0, Color::from_name)?,
+            "slot" => {
+                self.slot = match record.fields.get(0) {
+                    Some(&"none") => None,
+                    _ => Some(record.named(0, Slot::from_name)?)
+                };
+            },
+            "damage" => self.damage = Some(record.named(0, Dice::from_name)?),
+            "heal" => self.heal = Some(record.named(0, Dice::from_name)?),
+            "blast" => self.blast = Some(record.named(0, Dice::from_name)?),
+            "range" => {
+                self.range = record.field(0)?;
+                if self.range < 1 || self.range > MAX_RANGE {
+                    return Err(format!("Bad range on {}", record.at));
+                }
+            },
+            "effect" => {
+                self.effect = match record.fields.get(0) {
+                    Some(&"none") => None,
+                    _ => Some(record.named(0, ItemEffect::from_name)?)
+                };
+            },
+            _ => { return Err(record.unknown()); }
+        }
+        Ok(())
+    }
+
+    pub fn create(&self) -> Item {
+        let weapon: Option<Box<Weapon>> = self.damage.map(|damage| {
+            let thrown = self.effect == Some(ItemEffect::Blast);
+            let ranged = self.effect == Some(ItemEffect::Returns);
+            Box::new(DefinedWeapon::new(&self.name, damage, self.range, thrown, ranged)) as Box<Weapon>
+        });
+        Item {
+            name: self.name.clone(),
+            display_char: self.display_char,
+            foreground: self.foreground,
+            slot: self.slot,
+            weapon: weapon,
+            effect: self.effect,
+            heal: self.heal,
+            blast: self.blast
+        }
+    }
+}
+
+// Items are made from these by name, whether they are found in the dungeon,
+// carried from the start or read back from a save
+#[derive(Default)]
+pub struct ItemRegistry {
+    defs: Vec<ItemDef>
+}
+
+impl ItemRegistry {
+    pub fn load(problems: &mut Vec<String>) -> ItemRegistry {
+        load_or_builtin(Path::new(ITEM_DEFINITIONS_PATH), ITEM_DEFINITIONS, ItemRegistry::parse, problems)
+    }
+
+    pub fn parse(contents: &str) -> Result<ItemRegistry, String> {
+        let defs = blocks(contents, "item", ItemDef::new, ItemDef::read)?;
+        Ok(ItemRegistry { defs: defs })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ItemDef> {
+        self.defs.iter().find(|def| def.name == name)
+    }
+
+    pub fn create(&self, name: &str) -> Option<Item> {
+        self.get(name).map(|def| def.create())
+    }
+
+    pub fn defs(&self) -> &[ItemDef] {
+        &self.defs
+    }
+}
+
+// What an actor carries, in the order it was picked up
+#[derive(Clone)]
+pub struct Inventory {
+    pub capacity: usize,
+    items: Vec<Item>
+}
+
+impl Inventory {
+    pub fn new(capacity: usize) -> Inventory {
+        Inventory {
+            capacity: capacity,
+            items: vec![]
+        }
+    }
+
+    // Hands the item back when there is no room for it
+    pub fn add(&mut self, item: Item) -> Result<(), Item> {
+        if self.is_full() {
+            Err(item)
+        } else {
+            self.items.push(item);
+            Ok(())
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Item> {
+        if index < self.items.len() {
+            Some(self.items.remove(index))
+        } else {
+            None
+        }
+    }
+
+    pub fn items(&self) -> &[Item] {
+        &self.items
+    }
+
+    pub fn position_with_effect(&self, effect: ItemEffect) -> Option<usize> {
+        self.items.iter().position(|item| item.effect == Some(effect))
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.items.len() >= self.capacity
+    }
+}
+
+// What an actor has on, one item per slot
+#[derive(Clone, Default)]
+pub struct Equipment {
+    main_hand: Option<Item>,
+    off_hand: Option<Item>,
+    armor: Option<Item>
+}
+
+impl Equipment {
+    pub fn new() -> Equipment {
+        Equipment {
+            main_hand: None,
+            off_hand: None,
+            armor: None
+        }
+    }
+
+    pub fn get(&self, slot: Slot) -> Option<&Item> {
+        match slot {
+            Slot::MainHand => self.main_hand.as_ref(),
+            Slot::OffHand => self.off_hand.as_ref(),
+            Slot::Armor => self.armor.as_ref()
+        }
+    }
+
+    // Puts the item in its slot and hands back whatever was there before
+    pub fn equip(&mut self, slot: Slot, item: Item) -> Option<Item> {
+        self.slot_mut(slot).replace(item)
+    }
+
+    pub fn unequip(&mut self, slot: Slot) -> Option<Item> {
+        self.slot_mut(slot).take()
+    }
+
+    // Attacks are made with whatever is in the main hand
+    pub fn weapon(&self) -> Option<&Box<Weapon>> {
+        self.main_hand.as_ref().and_then(|item| item.weapon.as_ref())
+    }
+
+    fn slot_mut(&mut self, slot: Slot) -> &mut Option<Item> {
+        match slot {
+            Slot::MainHand => &mut self.main_hand,
+            Slot::OffHand => &mut self.off_hand,
+            Slot::Armor => &mut self.armor
+        }
+    }
+}
+
+// Items lying around one level. Several can share a tile; the one put down
+// last is on top.
+#[derive(Default)]
+pub struct FloorItems {
+    items: Vec<(Point, Item)>
+}
+
+impl FloorItems {
+    pub fn new() -> FloorItems {
+        FloorItems { items: vec![] }
+    }
+
+    pub fn put(&mut self, point: Point, item: Item) {
+        self.items.push((point, item));
+    }
+
+    pub fn take(&mut self, point: Point) -> Option<Item> {
+        match self.items.iter().rposition(|&(p, _)| p == point) {
+            Some(index) => Some(self.items.remove(index).1),
+            None => None
+        }
+    }
+
+    pub fn top_at(&self, point: Point) -> Option<&Item> {
+        self.items.iter().rev().find(|&&(p, _)| p == point).map(|&(_, ref item)| item)
+    }
+
+    pub fn all(&self) -> &[(Point, Item)] {
+        &self.items
+    }
+
+    pub fn render(&self, renderer: &mut Box<RenderingComponent>, fov: &FieldOfView) {
+        for &(point, ref item) in self.items.iter() {
+            if fov.is_visible(point) {
+                renderer.render_object(point, item.display_char, item.foreground, Color::Black);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_items_parse() {
+        let items = ItemRegistry::parse(ITEM_DEFINITIONS).unwrap();
+        let sword = items.create("Heroic Sworc").unwrap();
+        assert!(sword.slot == Some(Slot::MainHand));
+        assert!(sword.weapon.is_some());
+    }
+
+    #[test]
+    fn bad_colour() {
+        let error = ItemRegistry::parse("item Rock\ncolor mauve\n").err().unwrap();
+        assert_eq!(error, "Bad value 'mauve' on line 2");
+    }
+
+    #[test]
+    fn bad_dice() {
+        let error = ItemRegistry::parse("item Rock\ndamage 1d0\n").err().unwrap();
+        assert_eq!(error, "Bad value '1d0' on line 2");
+    }
+
+    #[test]
+    fn bad_range() {
+        let error = ItemRegistry::parse("item Rock\nrange 0\n").err().unwrap();
+        assert_eq!(error, "Bad range on line 2");
+        let error = ItemRegistry::parse("item Rock\nrange 1\nitem Spear\nrange -3\n").err().unwrap();
+        assert_eq!(error, "Bad range on line 4");
+        let error = ItemRegistry::parse("item Rock\nrange 79\n").err().unwrap();
+        assert_eq!(error, "Bad range on line 2");
+        assert!(ItemRegistry::parse("item Rock\nrange 78\n").is_ok());
+    }
+
+    #[test]
+    fn duplicate_item() {
+        let error = ItemRegistry::parse("item Rock\nrange 2\nitem Rock\nrange 3\n").err().unwrap();
+        assert_eq!(error, "item 'Rock' on line 3 is already defined");
+    }
+}
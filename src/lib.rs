@@ -6,4 +6,14 @@ pub mod movement;
 pub mod actor;
 pub mod input;
 pub mod map;
-pub mod combat;
\ No newline at end of file
+pub mod terrain;
+pub mod dungeon;
+pub mod fov;
+pub mod pathfinding;
+pub mod spatial;
+pub mod combat;
+pub mod item;
+pub mod definitions;
+pub mod monsters;
+pub mod rng;
+pub mod save;
\ No newline at end of file
@@ -1,27 +1,93 @@
 extern crate tcod;
 extern crate roguelike;
 
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::process;
+
 use roguelike::game::Game;
-use roguelike::input::GameKeyCode;
-use roguelike::input::GameKey::{SpecialKey};
+use roguelike::input::{InputSource, ScriptedInputSource};
+use roguelike::rendering::renderers::{RenderingComponent, TcodRenderingComponent};
+use roguelike::rng::GameRng;
+use roguelike::save;
+
+fn arg_value(args: &[String], flag: &str) -> Option<String> {
+    match args.iter().position(|arg| arg == flag) {
+        Some(index) => match args.get(index + 1) {
+            Some(value) => Some(value.clone()),
+            None => {
+                eprintln!("{} needs a value", flag);
+                process::exit(1);
+            }
+        },
+        None => None
+    }
+}
 
 fn main() {
-    let mut game = Game::new();
+    let args: Vec<String> = env::args().collect();
 
-    // render
-    game.render();
+    // --seed <n> starts a known run instead of a random one
+    let seed = match arg_value(&args, "--seed") {
+        Some(value) => match value.parse::<u32>() {
+            Ok(seed) => seed,
+            Err(_) => {
+                eprintln!("--seed needs a number, got {}", value);
+                process::exit(1);
+            }
+        },
+        None => GameRng::random_seed()
+    };
 
-    // our game loop
-    while !(game.rendering_component.window_closed() || game.exit) {
-        // wait for user input
-        let keypress = game.wait_for_keypress();
+    // --replay <file> plays back an input script before taking keyboard input
+    let replay = match arg_value(&args, "--replay") {
+        Some(path) => match ScriptedInputSource::from_file(Path::new(&path)) {
+            Ok(source) => Some(source),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        },
+        None => None
+    };
 
-        match keypress.key {
-            SpecialKey(GameKeyCode::Escape) => game.exit = true,
-            _ => {}
-        }
-        game.update();
+    let rc: Box<RenderingComponent> = match replay {
+        Some(source) => {
+            let source: Box<InputSource> = Box::new(source);
+            Box::new(TcodRenderingComponent::new_with_replay(Game::total_bounds(), source))
+        },
+        None => Box::new(TcodRenderingComponent::new(Game::total_bounds()))
+    };
+
+    // --load <file> continues a saved game, skipping the title screen
+    let mut game = match arg_value(&args, "--load") {
+        Some(path) => match save::load_game(Path::new(&path), rc) {
+            Ok(mut game) => {
+                game.save_path = path;
+                game
+            },
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        },
+        None => Game::new_at_title(rc, seed)
+    };
 
-        game.render();
+    // Abandoning or continuing a run changes the seed, but a replay starts
+    // from the one the game was started with
+    let start_seed = game.seed();
+    game.run();
+
+    // --record <file> writes the keys pressed this run, so that
+    // `--seed <n> --replay <file>` plays it back exactly
+    if let Some(path) = arg_value(&args, "--record") {
+        let script = format!("# seed {}\n{}\n", start_seed, ScriptedInputSource::to_script(&game.input_log));
+        match File::create(&path).and_then(|mut f| f.write_all(script.as_bytes())) {
+            Ok(_) => {},
+            Err(e) => eprintln!("Could not write input log {}: {}", path, e)
+        }
     }
 }
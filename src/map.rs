@@ -1,133 +1,951 @@
 extern crate core;
+extern crate rand;
 
 use rendering::windows::Windows;
-use rendering::renderers::RenderingComponent;
-use util::{Point, Bound};
-use actor::Actor;
+use rendering::renderers::{RenderingComponent, Color};
+use util::{Point, Bound, line};
+use pathfinding::distance;
+use actor::{Actor, ActorId, Diet, Stats};
+use movement::{Behavior, TICKS_PER_TURN, MOVE_COST, ATTACK_COST, FEED_COST};
+use combat::{
+  resolve_attack,
+  Attack,
+  AttackOutcome,
+  Claws,
+  Fists,
+  FlyingBoomerang,
+  LitBomb,
+  BOOMERANG_SPEED
+};
 use game::MoveInfo;
+use terrain::Terrain;
+use fov::FieldOfView;
+use spatial::{SpatialIndex, Occupancy};
+use item::{Item, FloorItems, ItemEffect, Slot};
+use monsters::{MonsterRegistry, Faction};
 use std::cell::RefCell;
+use std::mem;
 use std::rc::Rc;
 
 use self::core::ops::{Deref, DerefMut};
+use self::rand::Rng;
 
+// Percent chance that food calms down a hostile plant eater
+pub const TAME_CHANCE: u32 = 60;
+
+// Everything on one level of the dungeon
 pub struct Maps {
-  pub terrain: Box<Map>,
+  pub depth: u32,
+  // Ticks gone by on this level
+  pub clock: u32,
+  pub terrain: Rc<RefCell<Terrain>>,
+  pub occupancy: Rc<RefCell<Occupancy>>,
   pub enemies: Box<Map>,
   pub friends: Box<Map>,
-  pub pcs: Box<Map>
+  pub pcs: Box<Map>,
+  pub fov: FieldOfView,
+  pub items: FloorItems,
+  pub bombs: Vec<LitBomb>,
+  pub boomerangs: Vec<FlyingBoomerang>,
+  // Tiles caught by explosions this turn, drawn until the next one
+  pub blasts: Vec<Point>,
+  move_info: Rc<RefCell<MoveInfo>>
 }
 
 impl Maps {
-  pub fn new(move_info: Rc<RefCell<MoveInfo>>) -> Maps {
-    let terrain = Box::new(Map::new(move_info.clone()));
-    let enemies = Box::new(Map::new(move_info.clone()));
-    let friends = Box::new(Map::new(move_info.clone()));
-    let pcs = Box::new(Map::new(move_info.clone()));
+  pub fn new(move_info: Rc<RefCell<MoveInfo>>, fov_radius: i32) -> Maps {
+    let terrain = move_info.borrow().deref().terrain.clone();
+    let occupancy = move_info.borrow().deref().occupancy.clone();
+    let enemies = Box::new(Map::new(move_info.clone(), occupancy.clone()));
+    let friends = Box::new(Map::new(move_info.clone(), occupancy.clone()));
+    let pcs = Box::new(Map::new(move_info.clone(), occupancy.clone()));
+    let fov = {
+      FieldOfView::new(move_info.borrow().deref().bounds, fov_radius)
+    };
 
     Maps {
+      depth: 1,
+      clock: 0,
+      occupancy: occupancy,
       friends: friends,
       enemies: enemies,
       terrain: terrain,
-      pcs: pcs
+      pcs: pcs,
+      fov: fov,
+      items: FloorItems::new(),
+      bombs: vec![],
+      boomerangs: vec![],
+      blasts: vec![],
+      move_info: move_info
+    }
+  }
+
+  // A blank level at the given depth. It becomes the one the move info
+  // points at, see `make_current`.
+  pub fn new_level(move_info: Rc<RefCell<MoveInfo>>, depth: u32, fov_radius: i32) -> Maps {
+    {
+      let mut mi = move_info.borrow_mut();
+      let mi = mi.deref_mut();
+      mi.terrain = Rc::new(RefCell::new(Terrain::new(mi.bounds)));
+      mi.occupancy = Rc::new(RefCell::new(Occupancy::new(mi.bounds)));
+    }
+    let mut maps = Maps::new(move_info, fov_radius);
+    maps.depth = depth;
+    maps
+  }
+
+  // Movement components look at the level through the shared move info, so
+  // it has to be switched over whenever the heroine changes level
+  pub fn make_current(&self) {
+    let mut move_info = self.move_info.borrow_mut();
+    let move_info = move_info.deref_mut();
+    move_info.terrain = self.terrain.clone();
+    move_info.occupancy = self.occupancy.clone();
+  }
+
+  // Puts a creature from the registry down on the map of its faction, as
+  // tough as it comes at the depth of this level
+  pub fn spawn(&mut self, monsters: &MonsterRegistry, name: &str, point: Point) -> Option<ActorId> {
+    let def = match monsters.get(name) {
+      Some(def) => def,
+      None => { return None; }
+    };
+    let actor = Box::new(def.create(point.x, point.y, self.depth, self.move_info.clone()));
+    match def.faction {
+      Faction::Friend => Some(self.friends.push_actor(point, actor)),
+      Faction::Enemy => Some(self.enemies.push_actor(point, actor))
     }
   }
 
+  // The heroine acts, then time runs on tick by tick until she is ready
+  // again. Everyone else acts whenever they have saved up enough energy, so
+  // fast creatures get more turns than slow ones.
   pub fn update(&mut self, windows: &mut Windows) {
-    self.pcs.update(windows);
-    self.terrain.update(windows);
+    self.blasts.clear();
+    let attacks = self.pcs.update(windows);
+    // Walking into a friend swaps places with it instead of attacking it
+    let (swaps, attacks): (Vec<Attack>, Vec<Attack>) = attacks.into_iter().partition(|attack| self.friends.actor_at(attack.target).is_some());
+    for swap in swaps.iter() {
+      self.swap_with_friend(swap.target, windows);
+    }
+    self.resolve_pc_attacks(attacks, windows);
+    self.pass_time(windows);
+  }
+
+  // Only costs as much as the step it is
+  fn swap_with_friend(&mut self, target: Point, windows: &mut Windows) {
+    let char_location = {
+      self.move_info.borrow().deref().char_location
+    };
+    self.friends.move_actor(target, char_location);
+    self.pcs.move_actor(char_location, target);
+    { self.move_info.borrow_mut().deref_mut().char_location = target };
+    if let Some(pc) = self.player_mut() {
+      pc.energy += ATTACK_COST - MOVE_COST;
+    }
+    if let Some(friend) = self.friends.actor_at(char_location) {
+      windows.messages.buffer_message(&format!("You swap places with the {}.", friend.name));
+    }
+  }
+
+  // For what the heroine does through a prompt rather than by moving: she
+  // pays for it and time runs on just as after a move
+  pub fn spend_turn(&mut self, cost: i32, windows: &mut Windows) {
+    self.blasts.clear();
+    if let Some(pc) = self.player_mut() {
+      pc.energy -= cost;
+    }
+    self.pass_time(windows);
+  }
+
+  fn pass_time(&mut self, windows: &mut Windows) {
+    while !self.player_is_ready() {
+      self.tick(windows);
+    }
+  }
+
+  fn tick(&mut self, windows: &mut Windows) {
+    self.pcs.gain_energy();
+    self.friends.gain_energy();
+    self.enemies.gain_energy();
     self.friends.update(windows);
-    self.enemies.update(windows);
-    self.pcs.update(windows);
+    let attacks = self.enemies.update(windows);
+    self.resolve_enemy_attacks(attacks, windows);
+
+    // Fuses and flights go by whole turns
+    self.clock += 1;
+    if self.clock % TICKS_PER_TURN == 0 {
+      self.tick_bombs(windows);
+      self.fly_boomerangs(windows);
+    }
   }
 
-  pub fn render(&mut self, renderer: &mut Box<RenderingComponent>) {
-    self.terrain.render(renderer);
-    self.friends.render(renderer);
-    self.enemies.render(renderer);
-    self.pcs.render(renderer);
+  // Nothing left to wait for once she is dead
+  fn player_is_ready(&self) -> bool {
+    match self.player() {
+      Some(pc) => pc.is_ready() || pc.is_dead(),
+      None => true
+    }
   }
 
-  pub fn enemy_at(&self, point: Point) -> Option<&Box<Actor>> {
-    let enemies_at_point = &self.enemies.content[point.x as usize][point.y as usize];
-    if enemies_at_point.len() > 0 {
-      Some(&enemies_at_point[0])
+  // Sends the boomerang in the heroine's main hand off in a direction; it
+  // gets its first stretch of flight straight away
+  pub fn throw_boomerang(&mut self, direction: Point, windows: &mut Windows) {
+    let char_location = {
+      self.move_info.borrow().deref().char_location
+    };
+    let item = match self.player_mut() {
+      Some(pc) => pc.equipment.unequip(Slot::MainHand),
+      None => None
+    };
+    match item {
+      Some(item) => {
+        windows.messages.buffer_message(&format!("You throw the {}.", item.name));
+        self.boomerangs.push(FlyingBoomerang::new(item, char_location, direction));
+        self.fly_boomerangs(windows);
+        self.spend_turn(ATTACK_COST, windows);
+      },
+      None => {}
+    }
+  }
+
+  fn fly_boomerangs(&mut self, windows: &mut Windows) {
+    let mut flying = vec![];
+    for mut boomerang in mem::replace(&mut self.boomerangs, vec![]).into_iter() {
+      if self.fly_boomerang(&mut boomerang, windows) {
+        flying.push(boomerang);
+      }
+    }
+    self.boomerangs = flying;
+
+    for dead in self.enemies.remove_dead().iter() {
+      windows.messages.buffer_message(&format!("You killed the {}!", dead.name));
+    }
+    for dead in self.friends.remove_dead().iter() {
+      windows.messages.buffer_message(&format!("You killed the {}!", dead.name));
+    }
+  }
+
+  // Moves one boomerang for a turn; false once it has been caught or has
+  // dropped to the floor
+  fn fly_boomerang(&mut self, boomerang: &mut FlyingBoomerang, windows: &mut Windows) -> bool {
+    let weapon = match boomerang.item.weapon {
+      Some(ref weapon) => weapon.box_clone(),
+      None => Box::new(Fists::new())
+    };
+    let thrower = match self.player() {
+      Some(pc) => pc.stats,
+      None => Stats::new(0, 0, 0, 0)
+    };
+    let mut steps = 0;
+    while steps < BOOMERANG_SPEED {
+      if boomerang.has_arrived() {
+        if boomerang.returning {
+          // Nobody there to catch it
+          windows.messages.buffer_message(&format!("The {} drops to the floor.", weapon.get_name()));
+          self.items.put(boomerang.position, boomerang.item.clone());
+          return false;
+        }
+        boomerang.turn_back();
+      }
+
+      let next = boomerang.next_point();
+      if self.terrain.borrow().is_blocked(next) {
+        windows.messages.buffer_message(&format!("The {} hits the {} and drops.", weapon.get_name(), self.terrain.borrow().tile_at(next).name()));
+        self.items.put(boomerang.position, boomerang.item.clone());
+        return false;
+      }
+
+      if self.pcs.actor_at(next).is_some() {
+        self.catch_boomerang(next, boomerang.item.clone(), windows);
+        return false;
+      }
+
+      // A miss flies on past
+      let hit = {
+        match self.enemies.actor_at_mut(next).or(self.friends.actor_at_mut(next)) {
+          Some(actor) => {
+            let outcome = {
+              resolve_attack(&thrower, &actor.stats, weapon.deref(), &mut self.move_info.borrow_mut().deref_mut().rng)
+            };
+            match outcome {
+              AttackOutcome::Miss => {
+                windows.messages.buffer_message(&format!("The {} misses the {}.", weapon.get_name(), actor.name));
+                false
+              },
+              AttackOutcome::Hit(damage) => {
+                actor.take_damage(damage);
+                windows.messages.buffer_message(&format!("The {} hits the {} for {} points of damage!", weapon.get_name(), actor.name, damage));
+                true
+              },
+              AttackOutcome::Critical(damage) => {
+                actor.take_damage(damage);
+                windows.messages.buffer_message(&format!("The {} strikes the {} squarely for {} points of damage!", weapon.get_name(), actor.name, damage));
+                true
+              }
+            }
+          },
+          None => false
+        }
+      };
+      if hit {
+        if boomerang.returning {
+          windows.messages.buffer_message(&format!("The {} drops to the floor.", weapon.get_name()));
+          self.items.put(boomerang.position, boomerang.item.clone());
+          return false;
+        }
+        boomerang.turn_back();
+      } else {
+        boomerang.advance();
+      }
+      steps += 1;
+    }
+    true
+  }
+
+  fn catch_boomerang(&mut self, point: Point, item: Item, windows: &mut Windows) {
+    windows.messages.buffer_message(&format!("You catch the {}.", item.name));
+    let leftover = match self.pcs.actor_at_mut(point) {
+      Some(pc) => {
+        if pc.equipment.get(Slot::MainHand).is_none() {
+          pc.equipment.equip(Slot::MainHand, item);
+          None
+        } else {
+          pc.inventory.add(item).err()
+        }
+      },
+      None => Some(item)
+    };
+    if let Some(item) = leftover {
+      self.items.put(point, item);
+    }
+  }
+
+  // Throws the bomb in the heroine's main hand at the target. It flies until
+  // something is in the way and lands just short of it.
+  pub fn throw_bomb(&mut self, target: Point, windows: &mut Windows) {
+    let char_location = {
+      self.move_info.borrow().deref().char_location
+    };
+    let item = match self.player_mut() {
+      Some(pc) => pc.equipment.unequip(Slot::MainHand),
+      None => None
+    };
+    let item = match item {
+      Some(item) => item,
+      None => { return; }
+    };
+
+    let mut landing = char_location;
+    for point in line(char_location, target).into_iter().skip(1) {
+      let blocked = {
+        let move_info = self.move_info.borrow();
+        move_info.deref().tile_at(point).blocks_movement() || move_info.deref().is_occupied(point)
+      };
+      if blocked {
+        break;
+      }
+      landing = point;
+    }
+
+    // Being hit with a bomb in hand is nothing like being caught in the blast
+    let damage = match item.blast {
+      Some(blast) => blast.roll(&mut self.move_info.borrow_mut().deref_mut().rng),
+      None => 1
+    };
+    windows.messages.buffer_message(&format!("You throw the {}.", item.name));
+    self.bombs.push(LitBomb::new(landing, damage));
+    self.spend_turn(ATTACK_COST, windows);
+  }
+
+  fn tick_bombs(&mut self, windows: &mut Windows) {
+    let mut exploding = vec![];
+    let mut index = 0;
+    while index < self.bombs.len() {
+      if self.bombs[index].fuse <= 1 {
+        exploding.push(self.bombs.remove(index));
+      } else {
+        self.bombs[index].fuse -= 1;
+        index += 1;
+      }
+    }
+    for bomb in exploding.iter() {
+      self.explode(bomb, windows);
+    }
+  }
+
+  // Hurts everyone, friend or foe, the blast can reach; walls shield
+  fn explode(&mut self, bomb: &LitBomb, windows: &mut Windows) {
+    windows.messages.buffer_message("The bomb explodes!");
+
+    let radius = bomb.radius;
+    for x in (bomb.position.x - radius)..(bomb.position.x + radius + 1) {
+      for y in (bomb.position.y - radius)..(bomb.position.y + radius + 1) {
+        let point = Point::new(x, y);
+        let terrain = self.terrain.borrow();
+        let shielded = line(bomb.position, point).iter().any(|p| terrain.tile_at(*p).blocks_movement());
+        if !shielded {
+          self.blasts.push(point);
+        }
+      }
+    }
+
+    for point in self.blasts.clone().into_iter() {
+      let damage = bomb.damage_at(distance(bomb.position, point));
+      if let Some(pc) = self.pcs.actor_at_mut(point) {
+        pc.take_damage(damage);
+        windows.messages.buffer_message(&format!("You are caught in the blast for {} points of damage!", damage));
+      }
+      for map in [&mut self.friends, &mut self.enemies].iter_mut() {
+        if let Some(actor) = map.actor_at_mut(point) {
+          actor.take_damage(damage);
+          windows.messages.buffer_message(&format!("The {} is caught in the blast for {} points of damage!", actor.name, damage));
+        }
+      }
+    }
+
+    for map in [&mut self.friends, &mut self.enemies].iter_mut() {
+      for dead in map.remove_dead().iter() {
+        windows.messages.buffer_message(&format!("The {} dies in the blast.", dead.name));
+      }
+    }
+  }
+
+  // The heroine bumped into someone, or picked a direction to attack in:
+  // enemies get hit with whatever she is holding, friends just stay in her way
+  pub fn resolve_pc_attacks(&mut self, attacks: Vec<Attack>, windows: &mut Windows) {
+    for attack in attacks.iter() {
+      let weapon = match self.player().and_then(|pc| pc.weapon()) {
+        Some(weapon) => weapon.box_clone(),
+        None => Box::new(Fists::new())
+      };
+      match self.enemies.actor_at_mut(attack.target) {
+        Some(enemy) => {
+          let outcome = {
+            resolve_attack(&attack.stats, &enemy.stats, weapon.deref(), &mut self.move_info.borrow_mut().deref_mut().rng)
+          };
+          match outcome {
+            AttackOutcome::Miss => {
+              windows.messages.buffer_message(&format!("You swing your {} at the {} and miss.", weapon.get_name(), enemy.name));
+            },
+            AttackOutcome::Hit(damage) => {
+              enemy.take_damage(damage);
+              windows.messages.buffer_message(&format!("You attack the {} with your {} for {} points of damage!",
+                                                       enemy.name, weapon.get_name(), damage));
+            },
+            AttackOutcome::Critical(damage) => {
+              enemy.take_damage(damage);
+              windows.messages.buffer_message(&format!("Critical hit! You strike the {} with your {} for {} points of damage!",
+                                                       enemy.name, weapon.get_name(), damage));
+            }
+          }
+        },
+        None => windows.messages.buffer_message("No enemy in that direction!")
+      }
+    }
+
+    for dead in self.enemies.remove_dead().iter() {
+      windows.messages.buffer_message(&format!("You killed the {}!", dead.name));
+    }
+  }
+
+  fn resolve_enemy_attacks(&mut self, attacks: Vec<Attack>, windows: &mut Windows) {
+    let weapon = Claws::new();
+    for attack in attacks.iter() {
+      match self.pcs.actor_at_mut(attack.target) {
+        Some(pc) => {
+          let outcome = {
+            resolve_attack(&attack.stats, &pc.stats, &weapon, &mut self.move_info.borrow_mut().deref_mut().rng)
+          };
+          match outcome {
+            AttackOutcome::Miss => {
+              windows.messages.buffer_message(&format!("The {} misses you.", attack.attacker));
+            },
+            AttackOutcome::Hit(damage) => {
+              pc.take_damage(damage);
+              windows.messages.buffer_message(&format!("The {} hits you for {}!", attack.attacker, damage));
+            },
+            AttackOutcome::Critical(damage) => {
+              pc.take_damage(damage);
+              windows.messages.buffer_message(&format!("The {} lands a critical hit on you for {}!", attack.attacker, damage));
+            }
+          }
+        },
+        None => {}
+      }
+    }
+  }
+
+  pub fn player(&self) -> Option<&Box<Actor>> {
+    let char_location = {
+      self.move_info.borrow().deref().char_location
+    };
+    self.pcs.actor_at(char_location)
+  }
+
+  pub fn player_mut(&mut self) -> Option<&mut Box<Actor>> {
+    let char_location = {
+      self.move_info.borrow().deref().char_location
+    };
+    self.pcs.actor_at_mut(char_location)
+  }
+
+  // These all say whether the heroine got anything done, as only then
+  // does it take her any time
+  pub fn pick_up(&mut self, windows: &mut Windows) -> bool {
+    let char_location = {
+      self.move_info.borrow().deref().char_location
+    };
+    let item = match self.items.take(char_location) {
+      Some(item) => item,
+      None => {
+        windows.messages.buffer_message("There is nothing here to pick up.");
+        return false;
+      }
+    };
+    let name = item.name.clone();
+    let result = match self.player_mut() {
+      Some(pc) => pc.inventory.add(item),
+      None => Err(item)
+    };
+    match result {
+      Ok(_) => {
+        windows.messages.buffer_message(&format!("You pick up the {}.", name));
+        true
+      },
+      Err(item) => {
+        windows.messages.buffer_message("Your pack is full.");
+        self.items.put(char_location, item);
+        false
+      }
+    }
+  }
+
+  pub fn drop_item(&mut self, index: usize, windows: &mut Windows) -> bool {
+    let char_location = {
+      self.move_info.borrow().deref().char_location
+    };
+    match self.player_mut().and_then(|pc| pc.inventory.remove(index)) {
+      Some(item) => {
+        windows.messages.buffer_message(&format!("You drop the {}.", item.name));
+        self.items.put(char_location, item);
+        true
+      },
+      None => {
+        windows.messages.buffer_message("You don't have that.");
+        false
+      }
+    }
+  }
+
+  // Takes an item out of the pack into the slot it fits, putting back
+  // whatever was there
+  pub fn wield(&mut self, index: usize, windows: &mut Windows) -> bool {
+    let (done, message) = match self.player_mut() {
+      Some(pc) => {
+        let slot = pc.inventory.items().get(index).map(|item| item.slot);
+        match slot {
+          Some(Some(slot)) => {
+            let item = pc.inventory.remove(index).unwrap();
+            let name = item.name.clone();
+            match pc.equipment.equip(slot, item) {
+              Some(previous) => {
+                let previous_name = previous.name.clone();
+                let _ = pc.inventory.add(previous);
+                (true, format!("You put away the {} and wield the {}.", previous_name, name))
+              },
+              None => (true, format!("You wield the {}.", name))
+            }
+          },
+          Some(None) => (false, String::from("You can't wield that.")),
+          None => (false, String::from("You don't have that."))
+        }
+      },
+      None => { return false; }
+    };
+    windows.messages.buffer_message(&message);
+    done
+  }
+
+  pub fn unwield(&mut self, slot: Slot, windows: &mut Windows) -> bool {
+    let (done, message) = match self.player_mut() {
+      Some(pc) => {
+        if pc.equipment.get(slot).is_none() {
+          (false, String::from("You don't have anything there."))
+        } else if pc.inventory.is_full() {
+          (false, String::from("Your pack is full."))
+        } else {
+          let item = pc.equipment.unequip(slot).unwrap();
+          let message = format!("You put away the {}.", item.name);
+          let _ = pc.inventory.add(item);
+          (true, message)
+        }
+      },
+      None => { return false; }
+    };
+    windows.messages.buffer_message(&message);
+    done
+  }
+
+  // Offers the heroine's food to whoever stands at the target. Pets get
+  // healed and start following her; hostile plant eaters may be tamed,
+  // which makes them friends.
+  pub fn feed(&mut self, target: Point, windows: &mut Windows) {
+    let (index, food) = match self.player().and_then(|pc| {
+      pc.inventory.position_with_effect(ItemEffect::Feed).map(|index| (index, pc.inventory.items()[index].clone()))
+    }) {
+      Some(found) => found,
+      None => {
+        windows.messages.buffer_message("You have nothing to feed anyone.");
+        return;
+      }
+    };
+    let heal = match food.heal {
+      Some(heal) => heal.roll(&mut self.move_info.borrow_mut().deref_mut().rng) as i32,
+      None => 0
+    };
+
+    let fed = if let Some(friend) = self.friends.actor_at_mut(target) {
+      if friend.diet.is_some() {
+        let healed = friend.heal(heal);
+        friend.set_movement_component(Behavior::Follow.movement_component(self.move_info.clone()));
+        let looks = if healed > 0 { " and looks healthier" } else { "" };
+        windows.messages.buffer_message(&format!("The {} munches the {}{}. It follows you now.",
+                                                 friend.name, food.name, looks));
+        true
+      } else {
+        windows.messages.buffer_message(&format!("The {} isn't hungry.", friend.name));
+        false
+      }
+    } else if let Some(id) = self.enemies.id_at(target) {
+      let diet = self.enemies.get(id).and_then(|enemy| enemy.diet);
+      let name = self.enemies.get(id).map(|enemy| enemy.name.clone()).unwrap_or_default();
+      if diet == Some(Diet::Herbivore) {
+        let tamed = {
+          self.move_info.borrow_mut().deref_mut().rng.gen_range(0, 100) < TAME_CHANCE
+        };
+        if tamed {
+          let mut animal = self.enemies.remove_actor(id).unwrap();
+          animal.set_movement_component(Behavior::Follow.movement_component(self.move_info.clone()));
+          self.friends.push_actor(target, animal);
+          windows.messages.buffer_message(&format!("The {} eats the {} and calms down. It follows you now.", name, food.name));
+        } else {
+          windows.messages.buffer_message(&format!("The {} eats the {} but is still angry.", name, food.name));
+        }
+        true
+      } else {
+        windows.messages.buffer_message(&format!("The {} doesn't want the {}.", name, food.name));
+        false
+      }
     } else {
-      None
+      windows.messages.buffer_message("There is no one there to feed.");
+      false
+    };
+
+    if fed {
+      if let Some(pc) = self.player_mut() {
+        pc.inventory.remove(index);
+      }
+      self.spend_turn(FEED_COST, windows);
     }
   }
+
+  pub fn player_is_dead(&self) -> bool {
+    match self.player() {
+      Some(pc) => pc.is_dead(),
+      None => false
+    }
+  }
+
+  pub fn render(&mut self, renderer: &mut Box<RenderingComponent>) {
+    self.update_fov();
+    self.terrain.borrow().render(renderer, &self.fov);
+    self.items.render(renderer, &self.fov);
+    for bomb in self.bombs.iter() {
+      if self.fov.is_visible(bomb.position) {
+        renderer.render_object(bomb.position, '*', Color::Red, Color::Black);
+      }
+    }
+    self.friends.render(renderer, &self.fov);
+    self.enemies.render(renderer, &self.fov);
+    self.pcs.render(renderer, &self.fov);
+    for boomerang in self.boomerangs.iter() {
+      if self.fov.is_visible(boomerang.position) {
+        renderer.render_object(boomerang.position, boomerang.item.display_char, boomerang.item.foreground, Color::Black);
+      }
+    }
+    for point in self.blasts.iter() {
+      if self.fov.is_visible(*point) {
+        renderer.render_object(*point, '*', Color::White, Color::Red);
+      }
+    }
+  }
+
+  pub fn update_fov(&mut self) {
+    let char_location = {
+      self.move_info.borrow().deref().char_location
+    };
+    self.fov.compute(char_location, &self.terrain.borrow());
+  }
+
+  pub fn enemy_at(&self, point: Point) -> Option<&Box<Actor>> {
+    self.enemies.actor_at(point)
+  }
 }
 
+// One faction's actors. Each actor lives in a slot of the arena for as long
+// as it is on the map, so its id stays valid until it is removed. The slot
+// is then handed to the next actor pushed, so the arena never grows past
+// the most actors the map has held at once.
 pub struct Map {
-  pub content: Vec<Vec<Vec<Box<Actor>>>>,
+  actors: Vec<Option<Box<Actor>>>,
+  free: Vec<ActorId>,
+  live: usize,
+  index: SpatialIndex,
+  // Shared by every faction on the level, which needn't be the current one
+  occupancy: Rc<RefCell<Occupancy>>,
   pub size: Bound,
   pub move_info: Rc<RefCell<MoveInfo>>
 }
 
 impl Map {
-  pub fn new(move_info: Rc<RefCell<MoveInfo>>) -> Map {
+  pub fn new(move_info: Rc<RefCell<MoveInfo>>, occupancy: Rc<RefCell<Occupancy>>) -> Map {
     let size = {
       move_info.borrow().deref().bounds
     };
-    let content = Map::init_contents(size);
     Map {
-      content: content,
+      actors: vec![],
+      free: vec![],
+      live: 0,
+      index: SpatialIndex::new(size),
+      occupancy: occupancy,
       size: size,
       move_info: move_info
     }
   }
 
-  fn init_contents(size: Bound) -> Vec<Vec<Vec<Box<Actor>>>> {
-    let mut contents: Vec<Vec<Vec<Box<Actor>>>> = vec![];
-    for _ in 0..size.max.x {
-      let mut x_vec: Vec<Vec<Box<Actor>>> = vec![];
-      for _ in 0..size.max.y {
-        let y_vec: Vec<Box<Actor>> = vec![];
-        x_vec.push(y_vec);
+  pub fn push_actor(&mut self, point: Point, actor: Box<Actor>) -> ActorId {
+    let mut actor = actor;
+    actor.position = point;
+    let id = match self.free.pop() {
+      Some(id) => {
+        self.actors[id] = Some(actor);
+        id
+      },
+      None => {
+        self.actors.push(Some(actor));
+        self.actors.len() - 1
+      }
+    };
+    self.live += 1;
+    self.index.insert(id, point);
+    self.occupancy.borrow_mut().enter(point);
+    id
+  }
+
+  pub fn remove_actor(&mut self, id: ActorId) -> Option<Box<Actor>> {
+    let removed = match self.actors.get_mut(id) {
+      Some(slot) => slot.take(),
+      None => None
+    };
+    if let Some(ref actor) = removed {
+      self.free.push(id);
+      self.live -= 1;
+      self.index.remove(id, actor.position);
+      self.occupancy.borrow_mut().leave(actor.position);
+    }
+    removed
+  }
+
+  pub fn get(&self, id: ActorId) -> Option<&Box<Actor>> {
+    self.actors.get(id).and_then(|slot| slot.as_ref())
+  }
+
+  pub fn get_mut(&mut self, id: ActorId) -> Option<&mut Box<Actor>> {
+    self.actors.get_mut(id).and_then(|slot| slot.as_mut())
+  }
+
+  pub fn actors<'a>(&'a self) -> impl Iterator<Item=&'a Box<Actor>> + 'a {
+    self.actors.iter().filter_map(|slot| slot.as_ref())
+  }
+
+  pub fn len(&self) -> usize {
+    self.live
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.live == 0
+  }
+
+  // Puts whoever stands at `from` on `to` instead
+  pub fn move_actor(&mut self, from: Point, to: Point) {
+    if let Some(id) = self.id_at(from) {
+      if let Some(actor) = self.get_mut(id) {
+        actor.position = to;
       }
-      contents.push(x_vec);
+      self.relocate(id, from, to);
     }
-    return contents;
   }
 
-  pub fn push_actor(&mut self, point: Point, actor: Box<Actor>) {
-    self.content[point.x as usize][point.y as usize].push(actor);
+  fn relocate(&mut self, id: ActorId, from: Point, to: Point) {
+    self.index.move_actor(id, from, to);
+    let mut occupancy = self.occupancy.borrow_mut();
+    occupancy.leave(from);
+    occupancy.enter(to);
   }
 
-  pub fn update(&mut self, windows: &mut Windows) {
-    let mut new_content = Map::init_contents(self.size);
-    for x_iter in self.content.iter_mut() {
-      for y_iter in x_iter.iter_mut() {
-        for actor in y_iter.iter_mut() {
-          actor.update(windows);
+  // Everyone with enough energy gets to act
+  pub fn update(&mut self, windows: &mut Windows) -> Vec<Attack> {
+    let mut attacks = vec![];
+    for id in 0..self.actors.len() {
+      let (from, to, attack) = match self.actors[id] {
+        Some(ref mut actor) if actor.is_ready() => {
+          let from = actor.position;
+          let attack = actor.update(windows).map(|target| Attack { attacker: actor.name.clone(), stats: actor.stats, target: target });
           if actor.is_pc {
             { self.move_info.borrow_mut().deref_mut().char_location = actor.position };
           }
-          let point = actor.position;
-          let new_actor = actor.clone();
-          new_content[point.x as usize][point.y as usize].push(new_actor);
-        }
+          (from, actor.position, attack)
+        },
+        _ => continue
+      };
+
+      if from != to {
+        self.relocate(id, from, to);
+      }
+      if let Some(attack) = attack {
+        attacks.push(attack);
       }
     }
-    self.content = new_content;
+    attacks
   }
 
-  pub fn render(&mut self, renderer: &mut Box<RenderingComponent>) {
-    for (x, x_iter) in self.content.iter_mut().enumerate() {
-      for (y, y_iter) in x_iter.iter_mut().enumerate() {
-        for actor in y_iter.iter_mut() {
-          let point = Point::new(x as i32, y as i32);
-          renderer.render_object(point, actor.display_char, actor.foreground, actor.background);
+  pub fn gain_energy(&mut self) {
+    for slot in self.actors.iter_mut() {
+      if let Some(ref mut actor) = *slot {
+        actor.gain_energy();
+      }
+    }
+  }
+
+  // Only what is in view gets drawn
+  pub fn render(&mut self, renderer: &mut Box<RenderingComponent>, fov: &FieldOfView) {
+    for slot in self.actors.iter() {
+      if let Some(ref actor) = *slot {
+        if fov.is_visible(actor.position) {
+          renderer.render_object(actor.position, actor.display_char, actor.foreground, actor.background);
         }
       }
     }
   }
 
+  pub fn id_at(&self, point: Point) -> Option<ActorId> {
+    self.index.at(point).first().map(|id| *id)
+  }
+
   pub fn actor_at(&self, point: Point) -> Option<&Box<Actor>> {
-      let ref location = self.content[point.x as usize][point.y as usize];
-      if location.len() > 0 {
-          Some(&location[0])
-      } else {
-          None
+    match self.id_at(point) {
+      Some(id) => self.get(id),
+      None => None
+    }
+  }
+
+  pub fn actor_at_mut(&mut self, point: Point) -> Option<&mut Box<Actor>> {
+    match self.id_at(point) {
+      Some(id) => self.get_mut(id),
+      None => None
+    }
+  }
+
+  // Takes every actor whose health has run out off the map
+  pub fn remove_dead(&mut self) -> Vec<Box<Actor>> {
+    let mut dead = vec![];
+    for id in 0..self.actors.len() {
+      let is_dead = match self.actors[id] {
+        Some(ref actor) => actor.is_dead(),
+        None => false
+      };
+      if is_dead {
+        if let Some(actor) = self.remove_actor(id) {
+          dead.push(actor);
+        }
       }
+    }
+    dead
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use game::MoveInfo;
+  use movement::Behavior;
+  use rendering::renderers::Color;
+  use rng::GameRng;
+
+  fn kobold(move_info: &Rc<RefCell<MoveInfo>>) -> Box<Actor> {
+    let mc = Behavior::Random.movement_component(move_info.clone());
+    Box::new(Actor::new(0, 0, 'k', "kobold", mc, false, Color::Red, Color::Black, 3))
+  }
+
+  #[test]
+  fn removed_slots_are_reused() {
+    let move_info = Rc::new(RefCell::new(MoveInfo::new(Bound::new(0, 0, 80, 50), GameRng::new(1))));
+    let occupancy = move_info.borrow().occupancy.clone();
+    let mut map = Map::new(move_info.clone(), occupancy);
+    let first = map.push_actor(Point::new(1, 1), kobold(&move_info));
+    let second = map.push_actor(Point::new(2, 1), kobold(&move_info));
+    assert_eq!(map.len(), 2);
+
+    assert!(map.remove_actor(first).is_some());
+    assert!(map.remove_actor(first).is_none());
+    assert_eq!(map.len(), 1);
+    assert!(map.actor_at(Point::new(1, 1)).is_none());
+    assert!(!move_info.borrow().is_occupied(Point::new(1, 1)));
+
+    let third = map.push_actor(Point::new(3, 1), kobold(&move_info));
+    assert_eq!(third, first);
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.actors().count(), 2);
+    assert_eq!(map.get(second).map(|actor| actor.position.x), Some(2));
+    assert_eq!(map.id_at(Point::new(3, 1)), Some(third));
+  }
+
+  #[test]
+  fn dead_actors_give_up_their_slots() {
+    let move_info = Rc::new(RefCell::new(MoveInfo::new(Bound::new(0, 0, 80, 50), GameRng::new(1))));
+    let occupancy = move_info.borrow().occupancy.clone();
+    let mut map = Map::new(move_info.clone(), occupancy);
+    for x in 0..50 {
+      map.push_actor(Point::new(x, 1), kobold(&move_info));
+    }
+    for round in 0..20 {
+      for x in 0..50 {
+        map.actor_at_mut(Point::new(x, 1)).unwrap().take_damage(10);
+      }
+      assert_eq!(map.remove_dead().len(), 50, "round {}", round);
+      assert_eq!(map.len(), 0);
+      for x in 0..50 {
+        map.push_actor(Point::new(x, 1), kobold(&move_info));
+      }
+    }
+    // The arena never held more than fifty at once
+    assert_eq!(map.actors.len(), 50);
+  }
+
+  #[test]
+  fn maps_keep_their_own_occupancy() {
+    let bounds = Bound::new(0, 0, 80, 50);
+    let move_info = Rc::new(RefCell::new(MoveInfo::new(bounds, GameRng::new(1))));
+    // Like a level the heroine isn't on
+    let occupancy = Rc::new(RefCell::new(Occupancy::new(bounds)));
+    let mut map = Map::new(move_info.clone(), occupancy.clone());
+
+    let id = map.push_actor(Point::new(1, 1), kobold(&move_info));
+    assert!(occupancy.borrow().is_occupied(Point::new(1, 1)));
+    assert!(!move_info.borrow().is_occupied(Point::new(1, 1)));
+
+    map.remove_actor(id);
+    assert!(!occupancy.borrow().is_occupied(Point::new(1, 1)));
   }
 }
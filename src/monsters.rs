@@ -0,0 +1,214 @@
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+use actor::{Actor, Diet, Stats};
+use definitions::{Record, blocks, load_or_builtin};
+use game::MoveInfo;
+use movement::{Behavior, NORMAL_SPEED};
+use rendering::renderers::Color;
+
+pub const MONSTER_DEFINITIONS_PATH: &'static str = "data/monsters.txt";
+const MONSTER_DEFINITIONS: &'static str = include_str!("../data/monsters.txt");
+
+// Extra health enemies get for every level below the first
+pub const HEALTH_PER_DEPTH: i32 = 5;
+
+// Which side a creature starts out on
+#[derive(Copy, Clone, PartialEq)]
+pub enum Faction {
+    Friend,
+    Enemy
+}
+
+impl Faction {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Faction::Friend => "friend",
+            Faction::Enemy => "enemy"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Faction> {
+        match name {
+            "friend" => Some(Faction::Friend),
+            "enemy" => Some(Faction::Enemy),
+            _ => None
+        }
+    }
+}
+
+pub struct MonsterDef {
+    pub name: String,
+    pub display_char: char,
+    pub foreground: Color,
+    pub background: Color,
+    pub health: i32,
+    pub stats: Stats,
+    pub speed: i32,
+    pub diet: Option<Diet>,
+    pub behavior: Behavior,
+    pub faction: Faction,
+    pub min_depth: u32,
+    // None if it turns up all the way down
+    pub max_depth: Option<u32>
+}
+
+impl MonsterDef {
+    fn new(name: &str) -> MonsterDef {
+        MonsterDef {
+            name: String::from(name),
+            display_char: '?',
+            foreground: Color::White,
+            background: Color::Black,
+            health: 1,
+            stats: Stats::new(0, 0, 0, 0),
+            speed: NORMAL_SPEED,
+            diet: None,
+            behavior: Behavior::Random,
+            faction: Faction::Enemy,
+            min_depth: 1,
+            max_depth: None
+        }
+    }
+
+    fn read(&mut self, record: &Record) -> Result<(), String> {
+        match record.name {
+            "glyph" => self.display_char = record.char()?,
+            "colors" => {
+                self.foreground = record.named(0, Color::from_name)?;
+                self.background = record.named(1, Color::from_name)?;
+            },
+            "health" => self.health = record.field(0)?,
+            "stats" => self.stats = Stats::new(record.field(0)?, record.field(1)?, record.field(2)?, record.field(3)?),
+            "speed" => {
+                self.speed = record.field(0)?;
+                if self.speed < 0 {
+                    return Err(format!("Bad speed on {}", record.at));
+                }
+            },
+            "diet" => {
+                self.diet = match record.fields.get(0) {
+                    Some(&"none") => None,
+                    _ => Some(record.named(0, Diet::from_name)?)
+                };
+            },
+            "behavior" => self.behavior = record.named(0, Behavior::from_name)?,
+            "faction" => self.faction = record.named(0, Faction::from_name)?,
+            "depth" => {
+                self.min_depth = record.field(0)?;
+                self.max_depth = match record.fields.get(1) {
+                    Some(_) => Some(record.field(1)?),
+                    None => None
+                };
+            },
+            _ => { return Err(record.unknown()); }
+        }
+        Ok(())
+    }
+
+    pub fn spawns_at(&self, depth: u32) -> bool {
+        depth >= self.min_depth && self.max_depth.map_or(true, |max| depth <= max)
+    }
+
+    // Enemies get tougher the deeper they are found, friends stay the same
+    pub fn health_at(&self, depth: u32) -> i32 {
+        match self.faction {
+            Faction::Enemy => self.health + HEALTH_PER_DEPTH * (depth as i32 - 1),
+            Faction::Friend => self.health
+        }
+    }
+
+    pub fn create(&self, x: i32, y: i32, depth: u32, move_info: Rc<RefCell<MoveInfo>>) -> Actor {
+        let mc = self.behavior.movement_component(move_info);
+        let mut actor = Actor::new(x, y, self.display_char, &self.name, mc, false, self.foreground, self.background, self.health_at(depth));
+        actor.stats = self.stats;
+        actor.speed = self.speed;
+        actor.diet = self.diet;
+        actor
+    }
+}
+
+// Every kind of creature the dungeon knows about, by name
+#[derive(Default)]
+pub struct MonsterRegistry {
+    defs: Vec<MonsterDef>
+}
+
+impl MonsterRegistry {
+    pub fn load(problems: &mut Vec<String>) -> MonsterRegistry {
+        load_or_builtin(Path::new(MONSTER_DEFINITIONS_PATH), MONSTER_DEFINITIONS, MonsterRegistry::parse, problems)
+    }
+
+    pub fn parse(contents: &str) -> Result<MonsterRegistry, String> {
+        let defs = blocks(contents, "monster", MonsterDef::new, MonsterDef::read)?;
+        Ok(MonsterRegistry { defs: defs })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&MonsterDef> {
+        self.defs.iter().find(|def| def.name == name)
+    }
+
+    pub fn spawnable(&self, faction: Faction, depth: u32) -> Vec<&MonsterDef> {
+        self.defs.iter().filter(|def| def.faction == faction && def.spawns_at(depth)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rng::GameRng;
+    use util::Bound;
+
+    #[test]
+    fn shipped_monsters_parse() {
+        let monsters = MonsterRegistry::parse(MONSTER_DEFINITIONS).unwrap();
+        assert!(monsters.get("dog").is_some());
+        assert!(monsters.spawnable(Faction::Enemy, 1).len() > 0);
+    }
+
+    #[test]
+    fn bad_colour() {
+        let error = MonsterRegistry::parse("monster dog\nglyph d\ncolors white mauve\n").err().unwrap();
+        assert_eq!(error, "Bad value 'mauve' on line 3");
+    }
+
+    #[test]
+    fn unknown_record() {
+        let error = MonsterRegistry::parse("monster dog\nbark loud\n").err().unwrap();
+        assert_eq!(error, "Unknown record 'bark' on line 2");
+    }
+
+    #[test]
+    fn duplicate_monster() {
+        let error = MonsterRegistry::parse("monster kobold\nhealth 5\nmonster kobold\nhealth 50\n").err().unwrap();
+        assert_eq!(error, "monster 'kobold' on line 3 is already defined");
+    }
+
+    #[test]
+    fn field_before_the_header() {
+        let error = MonsterRegistry::parse("glyph d\nmonster dog\n").err().unwrap();
+        assert_eq!(error, "'glyph' before any monster on line 1");
+    }
+
+    #[test]
+    fn negative_speed() {
+        let error = MonsterRegistry::parse("monster statue\nspeed 0\nmonster snail\nspeed -1\n").err().unwrap();
+        assert_eq!(error, "Bad speed on line 4");
+    }
+
+    #[test]
+    fn enemies_get_tougher_deeper_down() {
+        let monsters = MonsterRegistry::parse(MONSTER_DEFINITIONS).unwrap();
+        let move_info = Rc::new(RefCell::new(MoveInfo::new(Bound::new(0, 0, 80, 50), GameRng::new(1))));
+        let kobold = monsters.get("kobold").unwrap();
+        let shallow = kobold.create(1, 1, 1, move_info.clone());
+        let deep = kobold.create(1, 1, 4, move_info.clone());
+        assert_eq!(shallow.health, kobold.health);
+        assert_eq!(deep.health, kobold.health + 3 * HEALTH_PER_DEPTH);
+        assert_eq!(deep.max_health, deep.health);
+
+        let dog = monsters.get("dog").unwrap();
+        assert_eq!(dog.create(1, 1, 4, move_info.clone()).health, dog.health);
+    }
+}
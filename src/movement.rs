@@ -11,6 +11,7 @@ use util::{
 use rendering::windows::Windows;
 use self::rand::Rng;
 use game::MoveInfo;
+use pathfinding::{find_path, uniform_cost, distance};
 
 use input::GameKey::{SpecialKey};
 use input::GameKeyCode;
@@ -18,11 +19,86 @@ use input::GameKeyCode;
 use std::cell::RefCell;
 use std::rc::Rc;
 
-use self::core::ops::Deref;
+use self::core::ops::{Deref, DerefMut};
+
+// Actors save up energy, as much as their speed every tick, and act once
+// they have a turn's worth. What they do costs some of it again.
+pub const TURN_ENERGY: i32 = 100;
+pub const NORMAL_SPEED: i32 = 10;
+pub const TICKS_PER_TURN: u32 = (TURN_ENERGY / NORMAL_SPEED) as u32;
+pub const MOVE_COST: i32 = 100;
+pub const ATTACK_COST: i32 = 120;
+pub const REST_COST: i32 = 50;
+// Handing over food. Throwing something costs as much as an attack.
+pub const FEED_COST: i32 = 100;
+// Picking up, dropping, wielding or putting away an item
+pub const ITEM_COST: i32 = 50;
+
+// What an actor wants to do with its turn
+#[derive(Copy, Clone)]
+pub enum Action {
+  Move(Point),
+  Attack(Point),
+  // Walking into a wall or off the map; nothing happens, so it takes no time
+  Blocked
+}
+
+impl Action {
+  // Moving onto the tile the actor is already on is resting
+  pub fn cost(&self, from: Point) -> i32 {
+    match *self {
+      Action::Move(point) if point == from => REST_COST,
+      Action::Move(_) => MOVE_COST,
+      Action::Attack(_) => ATTACK_COST,
+      Action::Blocked => 0
+    }
+  }
+}
 
 pub trait MovementComponent {
-  fn update(&self, Point, &mut Windows) -> Point;
+  fn update(&self, Point, &mut Windows) -> Action;
   fn box_clone(&self) -> Box<MovementComponent>;
+  fn behavior(&self) -> Behavior;
+}
+
+// Which kind of movement component an actor has, so it can be written out
+// and rebuilt against the shared move info
+#[derive(Copy, Clone, PartialEq)]
+pub enum Behavior {
+  Random,
+  User,
+  Aggro,
+  Follow
+}
+
+impl Behavior {
+  pub fn name(&self) -> &'static str {
+    match *self {
+      Behavior::Random => "random",
+      Behavior::User => "user",
+      Behavior::Aggro => "aggro",
+      Behavior::Follow => "follow"
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Behavior> {
+    match name {
+      "random" => Some(Behavior::Random),
+      "user" => Some(Behavior::User),
+      "aggro" => Some(Behavior::Aggro),
+      "follow" => Some(Behavior::Follow),
+      _ => None
+    }
+  }
+
+  pub fn movement_component(&self, move_info: Rc<RefCell<MoveInfo>>) -> Box<MovementComponent> {
+    match *self {
+      Behavior::Random => Box::new(RandomMovementComponent::new(move_info)),
+      Behavior::User => Box::new(UserMovementComponent::new(move_info)),
+      Behavior::Aggro => Box::new(AggroMovementComponent::new(move_info)),
+      Behavior::Follow => Box::new(FollowMovementComponent::new(move_info))
+    }
+  }
 }
 
 pub struct RandomMovementComponent {
@@ -36,29 +112,42 @@ impl RandomMovementComponent {
 }
 
 impl MovementComponent for RandomMovementComponent {
-  fn update(&self, point: Point, _: &mut Windows) -> Point {
+  fn update(&self, point: Point, _: &mut Windows) -> Action {
     let mut offset = Point { x: point.x, y: point.y };
-    let offset_x = rand::thread_rng().gen_range(0, 3i32) - 1;
+    let offset_x = self.move_info.borrow_mut().deref_mut().rng.gen_range(0, 3i32) - 1;
     let bound = {
       self.move_info.borrow().deref().bounds
     };
     match bound.contains(offset.offset_x(offset_x)) {
         Contains::DoesContain => offset = offset.offset_x(offset_x),
-        Contains::DoesNotContain => { return point; },
+        Contains::DoesNotContain => { return Action::Move(point); },
+    }
+    if self.move_info.borrow().deref().tile_at(offset).blocks_movement() {
+      return Action::Move(point);
     }
 
-    let offset_y = rand::thread_rng().gen_range(0, 3i32) - 1;
+    let offset_y = self.move_info.borrow_mut().deref_mut().rng.gen_range(0, 3i32) - 1;
     match bound.contains(offset.offset_y(offset_y)) {
         Contains::DoesContain => offset = offset.offset_y(offset_y),
-        Contains::DoesNotContain => { return point;},
+        Contains::DoesNotContain => { return Action::Move(point); },
+    }
+    if self.move_info.borrow().deref().tile_at(offset).blocks_movement() {
+      return Action::Move(point);
+    }
+
+    // Wandering creatures never push into someone else's tile
+    if offset != point && self.move_info.borrow().deref().is_occupied(offset) {
+      return Action::Move(point);
     }
 
-    offset
+    Action::Move(offset)
   }
 
   fn box_clone(&self) -> Box<MovementComponent> {
     Box::new(RandomMovementComponent { move_info: self.move_info.clone() })
   }
+
+  fn behavior(&self) -> Behavior { Behavior::Random }
 }
 
 pub struct UserMovementComponent {
@@ -72,7 +161,7 @@ impl UserMovementComponent {
 }
 
 impl MovementComponent for UserMovementComponent {
-  fn update(&self, point: Point, windows: &mut Windows) -> Point {
+  fn update(&self, point: Point, windows: &mut Windows) -> Action {
     let mut offset = Point { x: point.x, y: point.y };
     let last_keypress = {
       self.move_info.borrow().deref().last_keypress
@@ -103,10 +192,23 @@ impl MovementComponent for UserMovementComponent {
     };
 
     match bound.contains(offset) {
-      Contains::DoesContain => { offset }
+      Contains::DoesContain => {
+        let (tile, occupied) = {
+          let move_info = self.move_info.borrow();
+          (move_info.deref().tile_at(offset), move_info.deref().is_occupied(offset))
+        };
+        if tile.blocks_movement() {
+          windows.messages.buffer_message(&format!("There is a {} in the way.", tile.name()));
+          Action::Blocked
+        } else if offset != point && occupied {
+          Action::Attack(offset)
+        } else {
+          Action::Move(offset)
+        }
+      },
       Contains::DoesNotContain => {
         windows.messages.buffer_message("You can't move that way!");
-        point
+        Action::Blocked
       }
     }
   }
@@ -114,6 +216,8 @@ impl MovementComponent for UserMovementComponent {
   fn box_clone(&self) -> Box<MovementComponent> {
     Box::new(UserMovementComponent { move_info: self.move_info.clone() })
   }
+
+  fn behavior(&self) -> Behavior { Behavior::User }
 }
 
 pub struct AggroMovementComponent {
@@ -127,10 +231,28 @@ impl AggroMovementComponent {
 }
 
 impl MovementComponent for AggroMovementComponent {
-  fn update(&self, point: Point, _: &mut Windows) -> Point {
+  fn update(&self, point: Point, _: &mut Windows) -> Action {
     let char_point = {
       self.move_info.borrow().deref().char_location
     };
+
+    // Walk around walls and other actors when there is a way through,
+    // otherwise just head straight for the heroine
+    let next_step = {
+      let move_info = self.move_info.borrow();
+      let move_info = move_info.deref();
+      let terrain = move_info.terrain.borrow();
+      find_path(point, char_point, move_info.bounds,
+                |p| !terrain.is_blocked(p) && !move_info.is_occupied(p),
+                uniform_cost)
+        .and_then(|path| path.get(1).map(|p| *p))
+    };
+    match next_step {
+      Some(next) if next == char_point => { return Action::Attack(char_point); },
+      Some(next) => { return Action::Move(next); },
+      None => {}
+    }
+
     let mut offset = Point { x: 0, y: 0 };
 
     match point.compare_x(char_point) {
@@ -146,14 +268,18 @@ impl MovementComponent for AggroMovementComponent {
     }
 
     match point.offset(offset).compare(char_point) {
-      PointEquality::PointsEqual => point,
+      PointEquality::PointsEqual => Action::Attack(char_point),
       PointEquality::PointsNotEqual  => {
         let bound = {
           self.move_info.borrow().deref().bounds
         };
+        let blocked = {
+          let move_info = self.move_info.borrow();
+          move_info.deref().tile_at(point.offset(offset)).blocks_movement() || move_info.deref().is_occupied(point.offset(offset))
+        };
         match bound.contains(point.offset(offset)) {
-          Contains::DoesContain => point.offset(offset),
-          Contains::DoesNotContain => point
+          Contains::DoesContain if !blocked => Action::Move(point.offset(offset)),
+          _ => Action::Move(point)
         }
       }
     }
@@ -162,4 +288,44 @@ impl MovementComponent for AggroMovementComponent {
   fn box_clone(&self) -> Box<MovementComponent> {
     Box::new(AggroMovementComponent { move_info: self.move_info.clone() })
   }
+
+  fn behavior(&self) -> Behavior { Behavior::Aggro }
+}
+
+// Tags along behind the heroine, staying next to her once it catches up
+pub struct FollowMovementComponent {
+  move_info: Rc<RefCell<MoveInfo>>
+}
+
+impl FollowMovementComponent {
+  pub fn new(move_info: Rc<RefCell<MoveInfo>>) -> FollowMovementComponent {
+    FollowMovementComponent { move_info: move_info }
+  }
+}
+
+impl MovementComponent for FollowMovementComponent {
+  fn update(&self, point: Point, _: &mut Windows) -> Action {
+    let move_info = self.move_info.borrow();
+    let move_info = move_info.deref();
+    let char_point = move_info.char_location;
+    if distance(point, char_point) <= 1 {
+      return Action::Move(point);
+    }
+
+    let terrain = move_info.terrain.borrow();
+    let next_step = find_path(point, char_point, move_info.bounds,
+                              |p| !terrain.is_blocked(p) && !move_info.is_occupied(p),
+                              uniform_cost)
+      .and_then(|path| path.get(1).map(|p| *p));
+    match next_step {
+      Some(next) if next != char_point => Action::Move(next),
+      _ => Action::Move(point)
+    }
+  }
+
+  fn box_clone(&self) -> Box<MovementComponent> {
+    Box::new(FollowMovementComponent { move_info: self.move_info.clone() })
+  }
+
+  fn behavior(&self) -> Behavior { Behavior::Follow }
 }
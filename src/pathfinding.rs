@@ -0,0 +1,167 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use util::{Point, Bound, Contains};
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+struct Node {
+    cost: u32,
+    estimate: u32,
+    point: Point
+}
+
+// BinaryHeap is a max-heap, so the ordering is flipped to pop the node with
+// the lowest estimate first. Ties are broken on the point so the same map
+// always gives the same path.
+impl Ord for Node {
+    fn cmp(&self, other: &Node) -> Ordering {
+        other.estimate.cmp(&self.estimate)
+            .then_with(|| other.cost.cmp(&self.cost))
+            .then_with(|| other.point.x.cmp(&self.point.x))
+            .then_with(|| other.point.y.cmp(&self.point.y))
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Node) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Number of king moves between two points, which never overestimates
+// the cost of a path as long as every step costs at least 1
+pub fn distance(from: Point, to: Point) -> u32 {
+    let dx = (from.x - to.x).abs();
+    let dy = (from.y - to.y).abs();
+    if dx > dy { dx as u32 } else { dy as u32 }
+}
+
+pub fn uniform_cost(_: Point, _: Point) -> u32 {
+    1
+}
+
+// A* search over the grid inside `bounds`, moving in all eight directions.
+// `is_passable` decides which points can be walked through (the goal is
+// always allowed, so a path can end on an occupied tile) and `cost` prices
+// each step, which must be at least 1. The path starts at `from` and ends at
+// `to`; None means there is no way through.
+pub fn find_path<P, C>(from: Point, to: Point, bounds: Bound, is_passable: P, cost: C) -> Option<Vec<Point>>
+    where P: Fn(Point) -> bool, C: Fn(Point, Point) -> u32
+{
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<Point, Point> = HashMap::new();
+    let mut best: HashMap<Point, u32> = HashMap::new();
+
+    best.insert(from, 0);
+    open.push(Node { cost: 0, estimate: distance(from, to), point: from });
+
+    while let Some(node) = open.pop() {
+        if node.point == to {
+            return Some(rebuild_path(&came_from, from, to));
+        }
+        if node.cost > best[&node.point] {
+            continue;
+        }
+
+        for neighbour in neighbours(node.point).into_iter() {
+            match bounds.contains(neighbour) {
+                Contains::DoesContain => {},
+                Contains::DoesNotContain => continue
+            }
+            if neighbour != to && !is_passable(neighbour) {
+                continue;
+            }
+
+            let new_cost = node.cost + cost(node.point, neighbour);
+            let improved = match best.get(&neighbour) {
+                Some(&old_cost) => new_cost < old_cost,
+                None => true
+            };
+            if improved {
+                best.insert(neighbour, new_cost);
+                came_from.insert(neighbour, node.point);
+                open.push(Node { cost: new_cost, estimate: new_cost + distance(neighbour, to), point: neighbour });
+            }
+        }
+    }
+
+    None
+}
+
+fn neighbours(point: Point) -> Vec<Point> {
+    let mut points = vec![];
+    for dx in -1..2 {
+        for dy in -1..2 {
+            if dx != 0 || dy != 0 {
+                points.push(point.offset(Point::new(dx, dy)));
+            }
+        }
+    }
+    points
+}
+
+fn rebuild_path(came_from: &HashMap<Point, Point>, from: Point, to: Point) -> Vec<Point> {
+    let mut path = vec![to];
+    let mut current = to;
+    while current != from {
+        current = came_from[&current];
+        path.push(current);
+    }
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(from: Point, to: Point, walls: &[Point]) -> Option<Vec<Point>> {
+        find_path(from, to, Bound::new(0, 0, 10, 10), |p| !walls.contains(&p), uniform_cost)
+    }
+
+    fn assert_walkable(path: &[Point], walls: &[Point]) {
+        for step in path.windows(2) {
+            assert_eq!(distance(step[0], step[1]), 1);
+        }
+        assert!(path.iter().all(|p| !walls.contains(p)));
+    }
+
+    #[test]
+    fn straight_across_open_ground() {
+        let path = path(Point::new(1, 1), Point::new(6, 3), &[]).unwrap();
+        assert_eq!(path.len(), 6);
+        assert!(path[0] == Point::new(1, 1) && path[5] == Point::new(6, 3));
+        assert_walkable(&path, &[]);
+
+        assert_eq!(find_path(Point::new(4, 4), Point::new(4, 4), Bound::new(0, 0, 10, 10), |_| true, uniform_cost).map(|p| p.len()), Some(1));
+    }
+
+    #[test]
+    fn routes_around_a_wall() {
+        // A wall down column 5 with a gap at the bottom
+        let walls: Vec<Point> = (0..9).map(|y| Point::new(5, y)).collect();
+        let path = path(Point::new(2, 2), Point::new(8, 2), &walls).unwrap();
+        assert!(path[0] == Point::new(2, 2) && path[path.len() - 1] == Point::new(8, 2));
+        assert!(path.contains(&Point::new(5, 9)));
+        assert_walkable(&path, &walls);
+        // Down to the gap and back up again, seven steps each way
+        assert_eq!(path.len(), 15);
+    }
+
+    #[test]
+    fn no_path_to_a_walled_in_goal() {
+        let goal = Point::new(7, 7);
+        let walls: Vec<Point> = (6..9).flat_map(|x| (6..9).map(move |y| Point::new(x, y))).filter(|&p| p != goal).collect();
+        assert!(path(Point::new(1, 1), goal, &walls).is_none());
+
+        // Or one that is off the grid
+        assert!(path(Point::new(1, 1), Point::new(12, 1), &[]).is_none());
+    }
+
+    #[test]
+    fn the_goal_may_be_occupied() {
+        let goal = Point::new(3, 1);
+        let path = path(Point::new(1, 1), goal, &[goal]).unwrap();
+        assert_eq!(path.len(), 3);
+    }
+}
@@ -1,18 +1,53 @@
+#[cfg(feature = "tcod")]
 extern crate tcod;
 
-use self::tcod::{Console, RootConsole, TextAlignment};
+#[cfg(feature = "tcod")]
+use self::tcod::{Console, RootConsole, BackgroundFlag, TextAlignment};
+#[cfg(feature = "tcod")]
 use self::tcod::input::Key;
 
 use util::{Point, Bound};
-use input::{TcodInputComponent, InputComponent, KeyboardInput};
+use input::{KeyboardInput, GameKey, GameKeyCode, InputSource, ScriptedInputSource};
+#[cfg(feature = "tcod")]
+use input::{TcodInputComponent, InputComponent};
 use rendering::windows::WindowComponent;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 pub enum Color {
     Red,
     Blue,
     Black,
-    White
+    White,
+    Grey,
+    DarkGrey,
+    Green
+}
+
+impl Color {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Color::Red   => "red",
+            Color::Blue  => "blue",
+            Color::Black => "black",
+            Color::White => "white",
+            Color::Grey  => "grey",
+            Color::DarkGrey => "dark_grey",
+            Color::Green => "green"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Color> {
+        match name {
+            "red"   => Some(Color::Red),
+            "blue"  => Some(Color::Blue),
+            "black" => Some(Color::Black),
+            "white" => Some(Color::White),
+            "grey"  => Some(Color::Grey),
+            "dark_grey" => Some(Color::DarkGrey),
+            "green" => Some(Color::Green),
+            _ => None
+        }
+    }
 }
 
 pub trait RenderingComponent {
@@ -22,14 +57,19 @@ pub trait RenderingComponent {
     fn wait_for_keypress(&mut self) -> KeyboardInput;
     fn window_closed(&mut self) -> bool;
     fn attach_window(&mut self, window: &mut Box<WindowComponent>);
-    fn translate_color(&self, Color) -> tcod::Color;
+
+    // The last frame drawn, as text, for renderers that keep it around
+    fn frame_text(&self) -> Option<String> { None }
 }
 
+#[cfg(feature = "tcod")]
 pub struct TcodRenderingComponent {
     console: RootConsole,
-    input_component: Box<InputComponent<Key>>
+    input_component: Box<InputComponent<Key>>,
+    replay: Option<Box<InputSource>>
 }
 
+#[cfg(feature = "tcod")]
 impl TcodRenderingComponent {
     pub fn new(bounds: Bound) -> TcodRenderingComponent {
         let console = RootConsole::initializer().size(bounds.max.x + 1, bounds.max.y + 1).title("Roguelike!").init();
@@ -38,11 +78,32 @@ impl TcodRenderingComponent {
 
         TcodRenderingComponent {
             console: console,
-            input_component: ic
+            input_component: ic,
+            replay: None
+        }
+    }
+
+    // Plays back the given keypresses before handing control to the keyboard
+    pub fn new_with_replay(bounds: Bound, replay: Box<InputSource>) -> TcodRenderingComponent {
+        let mut rc = TcodRenderingComponent::new(bounds);
+        rc.replay = Some(replay);
+        rc
+    }
+
+    fn translate_color(&self, input: Color) -> tcod::Color {
+        match input {
+            Color::Red   => tcod::Color::new(255u8, 0u8, 0u8),
+            Color::Blue  => tcod::Color::new(0u8, 0u8, 255u8),
+            Color::White => tcod::Color::new(255u8, 255u8, 255u8),
+            Color::Black => tcod::Color::new(0u8, 0u8, 0u8),
+            Color::Grey  => tcod::Color::new(128u8, 128u8, 128u8),
+            Color::DarkGrey => tcod::Color::new(48u8, 48u8, 48u8),
+            Color::Green => tcod::Color::new(0u8, 255u8, 0u8)
         }
     }
 }
 
+#[cfg(feature = "tcod")]
 impl RenderingComponent for TcodRenderingComponent {
     fn before_render_new_frame(&mut self) {
         self.console.clear();
@@ -59,6 +120,9 @@ impl RenderingComponent for TcodRenderingComponent {
     }
 
     fn wait_for_keypress(&mut self) -> KeyboardInput {
+        if let Some(keypress) = self.replay.as_mut().and_then(|r| r.next_keypress()) {
+            return keypress;
+        }
         let k = self.console.wait_for_keypress(true);
         self.input_component.translate_input(k)
     }
@@ -68,33 +132,194 @@ impl RenderingComponent for TcodRenderingComponent {
     }
 
     fn attach_window(&mut self, window: &mut Box<WindowComponent>) {
-        window.clear();
-        let mut line = 0i32;
         let bounds = window.get_bounds();
-        let messages = window.get_messages();
+        let background = self.translate_color(window.get_bg_color());
+        let foreground = self.translate_color(Color::White);
+
+        for y in bounds.min.y..(bounds.max.y + 1) {
+            for x in bounds.min.x..(bounds.max.x + 1) {
+                self.console.put_char_ex(x, y, ' ', foreground, background);
+            }
+        }
 
-        for message in messages.iter() {
-            window.print_message(0, line, TextAlignment::Left, &message[..]);
+        self.console.set_default_foreground(foreground);
+        self.console.set_default_background(background);
+        let width = (bounds.max.x - bounds.min.x + 1) as usize;
+        let mut line = 0i32;
+        for message in window.get_messages().iter() {
+            let text: String = message.chars().take(width).collect();
+            self.console.print_ex(bounds.min.x, bounds.min.y + line, BackgroundFlag::Set, TextAlignment::Left, &text[..]);
             line = line + 1;
         }
+    }
+}
 
-        let console = window.get_console();
+#[derive(Copy, Clone, PartialEq)]
+pub struct HeadlessCell {
+    pub glyph: char,
+    pub foreground: Color,
+    pub background: Color
+}
 
-        tcod::console::blit(&*console,
-                            (0, 0),
-                            (bounds.max.x + 1, bounds.max.y + 1),
-                            &mut self.console,
-                            (bounds.min.x, bounds.min.y),
-                            1f32,
-                            1f32);
+impl HeadlessCell {
+    pub fn blank() -> HeadlessCell {
+        HeadlessCell {
+            glyph: ' ',
+            foreground: Color::White,
+            background: Color::Black
+        }
     }
+}
 
-    fn translate_color(&self, input: Color) -> tcod::Color {
-        match input {
-            Color::Red   => tcod::Color::new(255u8, 0u8, 0u8),
-            Color::Blue  => tcod::Color::new(0u8, 0u8, 255u8),
-            Color::White => tcod::Color::new(255u8, 255u8, 255u8),
-            Color::Black => tcod::Color::new(0u8, 0u8, 0u8)
+// Keeps the frame in memory instead of drawing it, so the game can run
+// without a display or libtcod (tests, servers). Keypresses come from an
+// input source; once it runs dry the "window" reports itself closed.
+pub struct HeadlessRenderingComponent {
+    bounds: Bound,
+    cells: Vec<Vec<HeadlessCell>>,
+    last_frame: Vec<Vec<HeadlessCell>>,
+    input_source: Box<InputSource>
+}
+
+impl HeadlessRenderingComponent {
+    pub fn new(bounds: Bound) -> HeadlessRenderingComponent {
+        HeadlessRenderingComponent::new_with_keypresses(bounds, vec![])
+    }
+
+    pub fn new_with_keypresses(bounds: Bound, keypresses: Vec<KeyboardInput>) -> HeadlessRenderingComponent {
+        let source: Box<InputSource> = Box::new(ScriptedInputSource::new(keypresses));
+        HeadlessRenderingComponent::new_with_input_source(bounds, source)
+    }
+
+    pub fn new_with_input_source(bounds: Bound, input_source: Box<InputSource>) -> HeadlessRenderingComponent {
+        let cells = HeadlessRenderingComponent::blank_cells(bounds);
+        HeadlessRenderingComponent {
+            bounds: bounds,
+            cells: cells.clone(),
+            last_frame: cells,
+            input_source: input_source
+        }
+    }
+
+    pub fn cell_at(&self, point: Point) -> Option<HeadlessCell> {
+        if point.x < 0 || point.y < 0 {
+            return None;
+        }
+        self.last_frame.get(point.y as usize)
+                       .and_then(|row| row.get(point.x as usize))
+                       .map(|cell| *cell)
+    }
+
+    pub fn last_frame_lines(&self) -> Vec<String> {
+        self.last_frame.iter()
+                       .map(|row| row.iter().map(|cell| cell.glyph).collect::<String>())
+                       .collect()
+    }
+
+    pub fn last_frame_text(&self) -> String {
+        self.last_frame_lines().join("\n")
+    }
+
+    fn blank_cells(bounds: Bound) -> Vec<Vec<HeadlessCell>> {
+        let width = (bounds.max.x + 1) as usize;
+        let height = (bounds.max.y + 1) as usize;
+        vec![vec![HeadlessCell::blank(); width]; height]
+    }
+
+    fn put_cell(&mut self, position: Point, cell: HeadlessCell) {
+        if position.x < 0 || position.y < 0 || position.x > self.bounds.max.x || position.y > self.bounds.max.y {
+            return;
+        }
+        self.cells[position.y as usize][position.x as usize] = cell;
+    }
+}
+
+impl RenderingComponent for HeadlessRenderingComponent {
+    fn before_render_new_frame(&mut self) {
+        self.cells = HeadlessRenderingComponent::blank_cells(self.bounds);
+    }
+
+    fn render_object(&mut self, position: Point, symbol: char, foreground: Color, background: Color) {
+        self.put_cell(position, HeadlessCell { glyph: symbol, foreground: foreground, background: background });
+    }
+
+    fn after_render_new_frame(&mut self) {
+        self.last_frame = self.cells.clone();
+    }
+
+    fn wait_for_keypress(&mut self) -> KeyboardInput {
+        match self.input_source.next_keypress() {
+            Some(keypress) => keypress,
+            None => KeyboardInput { key: GameKey::SpecialKey(GameKeyCode::Escape) }
         }
     }
+
+    fn window_closed(&mut self) -> bool {
+        self.input_source.is_exhausted()
+    }
+
+    fn frame_text(&self) -> Option<String> {
+        Some(self.last_frame_text())
+    }
+
+    // Cleared and written the same way as on the tcod console
+    fn attach_window(&mut self, window: &mut Box<WindowComponent>) {
+        let bounds = window.get_bounds();
+        let blank = HeadlessCell { background: window.get_bg_color(), ..HeadlessCell::blank() };
+        for y in bounds.min.y..(bounds.max.y + 1) {
+            for x in bounds.min.x..(bounds.max.x + 1) {
+                self.put_cell(Point::new(x, y), blank);
+            }
+        }
+
+        let messages = window.get_messages();
+        for (line, message) in messages.iter().enumerate() {
+            let y = bounds.min.y + line as i32;
+            if y > bounds.max.y {
+                break;
+            }
+            for (column, glyph) in message.chars().enumerate() {
+                let x = bounds.min.x + column as i32;
+                if x > bounds.max.x {
+                    break;
+                }
+                self.put_cell(Point::new(x, y), HeadlessCell { glyph: glyph, ..blank });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct RedWindow {
+        messages: Vec<Box<String>>
+    }
+
+    impl WindowComponent for RedWindow {
+        fn get_bounds(&self) -> Bound { Bound::new(2, 1, 6, 2) }
+        fn get_bg_color(&self) -> Color { Color::Red }
+        fn get_mut_messages(&mut self) -> &mut Vec<Box<String>> { &mut self.messages }
+        fn get_messages(&self) -> Vec<Box<String>> { self.messages.clone() }
+        fn get_max_messages(&self) -> u32 { 2 }
+    }
+
+    #[test]
+    fn headless_windows_are_cleared_with_their_background() {
+        let mut rc = HeadlessRenderingComponent::new(Bound::new(0, 0, 9, 3));
+        let mut window: Box<WindowComponent> = Box::new(RedWindow { messages: vec![] });
+        window.buffer_message("Hi");
+        rc.before_render_new_frame();
+        rc.attach_window(&mut window);
+        rc.after_render_new_frame();
+
+        assert_eq!(rc.last_frame_lines()[1], "  Hi      ");
+        let text = rc.cell_at(Point::new(2, 1)).unwrap();
+        assert!(text.glyph == 'H' && text.foreground == Color::White && text.background == Color::Red);
+        assert!(rc.cell_at(Point::new(6, 2)).unwrap().background == Color::Red);
+        // Outside the window is left alone
+        assert!(rc.cell_at(Point::new(7, 1)).unwrap() == HeadlessCell::blank());
+        assert!(rc.cell_at(Point::new(2, 0)).unwrap() == HeadlessCell::blank());
+    }
 }
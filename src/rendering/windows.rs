@@ -1,12 +1,8 @@
-extern crate tcod;
-
-use self::tcod::{Console, BackgroundFlag, Color, OffscreenConsole, TextAlignment};
-
 use util::Bound;
+use rendering::renderers::Color;
 
 macro_rules! window_component_getters {
     () => {
-        fn get_console(&mut self) -> &mut OffscreenConsole { &mut self.console }
         fn get_bounds(&self) -> Bound { self.bounds }
         fn get_bg_color(&self) -> Color { self.background_color }
 
@@ -27,7 +23,6 @@ macro_rules! window_component_getters {
 macro_rules! window_component_def {
     ($name:ident) => {
         pub struct $name {
-            console: OffscreenConsole,
             background_color: Color,
             bounds: Bound,
             messages: Vec<Box<String>>,
@@ -39,12 +34,7 @@ macro_rules! window_component_def {
 macro_rules! window_component_init {
     ($name:ident, $color:expr, $max_messages:expr) => {
         pub fn new(bounds: Bound) -> $name {
-            let height = bounds.max.y - bounds.min.y + 1;
-            let width = bounds.max.x - bounds.min.x + 1;
-            let console = OffscreenConsole::new(width, height);
-
             $name {
-                console: console,
                 background_color: $color,
                 bounds: bounds,
                 messages: vec![],
@@ -57,19 +47,6 @@ macro_rules! window_component_init {
 pub trait WindowComponent {
     fn get_bounds(&self) -> Bound;
     fn get_bg_color(&self) -> Color;
-    fn get_console(&mut self) -> &mut OffscreenConsole;
-
-    fn clear(&mut self) {
-        let color = self.get_bg_color();
-        let mut console = self.get_console();
-        console.set_default_background(color);
-        console.clear();
-    }
-
-    fn print_message(&mut self, x: i32, y: i32, alignment: TextAlignment, text: &str) {
-        let mut console = self.get_console();
-        console.print_ex(x, y, BackgroundFlag::Set, alignment, text);
-    }
 
     fn buffer_message(&mut self, text: &str) {
         let max = self.get_max_messages();
@@ -97,35 +74,35 @@ pub trait WindowComponent {
     fn get_max_messages(&self) -> u32;
 }
 
-window_component_def!(TcodStatsWindowComponent);
-impl TcodStatsWindowComponent {
-    window_component_init!(TcodStatsWindowComponent, Color::new(0u8, 0u8, 0u8), 10u32);
+window_component_def!(StatsWindowComponent);
+impl StatsWindowComponent {
+    window_component_init!(StatsWindowComponent, Color::Black, 10u32);
 }
-impl WindowComponent for TcodStatsWindowComponent {
+impl WindowComponent for StatsWindowComponent {
     window_component_getters!();
 }
 
-window_component_def!(TcodInputWindowComponent);
-impl TcodInputWindowComponent {
-    window_component_init!(TcodInputWindowComponent, Color::new(0u8, 0u8, 0u8), 2u32);
+window_component_def!(InputWindowComponent);
+impl InputWindowComponent {
+    window_component_init!(InputWindowComponent, Color::Black, 2u32);
 }
-impl WindowComponent for TcodInputWindowComponent {
+impl WindowComponent for InputWindowComponent {
     window_component_getters!();
 }
 
-window_component_def!(TcodMessagesWindowComponent);
-impl TcodMessagesWindowComponent {
-    window_component_init!(TcodMessagesWindowComponent, Color::new(0u8, 0u8, 0u8), 9u32);
+window_component_def!(MessagesWindowComponent);
+impl MessagesWindowComponent {
+    window_component_init!(MessagesWindowComponent, Color::Black, 9u32);
 }
-impl WindowComponent for TcodMessagesWindowComponent {
+impl WindowComponent for MessagesWindowComponent {
     window_component_getters!();
 }
 
-window_component_def!(TcodMapWindowComponent);
-impl TcodMapWindowComponent {
-    window_component_init!(TcodMapWindowComponent, Color::new(0u8, 0u8, 0u8), 10u32);
+window_component_def!(MapWindowComponent);
+impl MapWindowComponent {
+    window_component_init!(MapWindowComponent, Color::Black, 10u32);
 }
-impl WindowComponent for TcodMapWindowComponent {
+impl WindowComponent for MapWindowComponent {
     window_component_getters!();
 }
 
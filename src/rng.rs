@@ -0,0 +1,64 @@
+extern crate rand;
+
+use self::rand::Rng;
+
+// The one random number generator for the whole game. It is a plain
+// xorshift128 so the sequence only depends on the seed, never on the
+// platform, which is what makes a seed plus an input log replayable.
+#[derive(Clone)]
+pub struct GameRng {
+    seed: u32,
+    state: [u32; 4]
+}
+
+impl GameRng {
+    pub fn new(seed: u32) -> GameRng {
+        // Spread the seed over the whole state with splitmix64 so that
+        // neighbouring seeds give unrelated runs
+        let mut x = seed as u64;
+        let mut state = [0u32; 4];
+        for word in state.iter_mut() {
+            x = x.wrapping_add(0x9E3779B97F4A7C15);
+            let mut z = x;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+            *word = (z ^ (z >> 31)) as u32;
+        }
+        GameRng::new_with_state(seed, state)
+    }
+
+    pub fn new_with_state(seed: u32, state: [u32; 4]) -> GameRng {
+        let mut state = state;
+        // xorshift gets stuck on an all zero state
+        if state == [0u32; 4] {
+            state[0] = 1;
+        }
+        GameRng {
+            seed: seed,
+            state: state
+        }
+    }
+
+    pub fn random_seed() -> u32 {
+        rand::thread_rng().gen()
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn state(&self) -> [u32; 4] {
+        self.state
+    }
+}
+
+impl Rng for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        let t = self.state[0] ^ (self.state[0] << 11);
+        self.state[0] = self.state[1];
+        self.state[1] = self.state[2];
+        self.state[2] = self.state[3];
+        self.state[3] = self.state[3] ^ (self.state[3] >> 19) ^ (t ^ (t >> 8));
+        self.state[3]
+    }
+}
@@ -0,0 +1,633 @@
+extern crate core;
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+use game::Game;
+use actor::{Actor, Diet, Stats};
+use map::{Map, Maps};
+use game::MoveInfo;
+use movement::{Behavior, NORMAL_SPEED, TURN_ENERGY};
+use rendering::renderers::{Color, RenderingComponent};
+use game_states::{GameState, MovementGameState, AttackInputGameState, InventoryGameState, InventoryAction, ThrowGameState, FeedGameState, GameOverGameState, PauseGameState};
+use combat::{Weapon, Fists, LitBomb, FlyingBoomerang};
+use definitions::{Record, records};
+use item::{ItemRegistry, Slot};
+use rng::GameRng;
+use terrain::Tile;
+use util::Point;
+
+use self::core::ops::{Deref, DerefMut};
+
+// Save files are plain text, one record per line, e.g.
+//
+//   roguelike-save 12
+//   seed 1234
+//   rng 1 2 3 4
+//   player 40 25
+//   state movement
+//   state throw 41 25
+//   depth 2
+//   level 1
+//   clock 240
+//   tiles 0 ########...
+//   explored 0 0001110...
+//   actor enemies 20 20 107 red black 12 20 none aggro kobold
+//   stats 0 1 0 1
+//   speed 10 60
+//   equip main_hand Heroic Sworc
+//   carry Little Boomerang
+//   item 12 30 Little Boomerang
+//   bomb 14 30 2 12
+//   boomerang 15 30 40 25 46 25 0 Little Boomerang
+//   message You can't move that way!
+//
+// `stats` (accuracy, evasion, strength and defense), `speed` (speed and
+// saved up energy), `equip` and `carry` records give the combat stats, the
+// turn scheduling, the equipment and the inventory of the actor just before
+// them. `clock` (ticks gone by), `tiles`, `explored`, `actor`, `item`,
+// `bomb` (lit, with the turns left on its fuse and the damage it will do)
+// and `boomerang` (in flight: position, thrower, turn point, whether it is
+// on its way back and the item) records belong to the `level` above them.
+// Items are saved by name and rebuilt from the item definitions on load.
+// There is a `state` record for each game state on the stack, bottom first.
+// Every level the heroine has visited is written out, `depth` says which
+// one she is on.
+//
+// Bump SAVE_VERSION whenever a record changes shape.
+pub const SAVE_VERSION: u32 = 12;
+pub const DEFAULT_SAVE_PATH: &'static str = "roguelike.sav";
+const SAVE_HEADER: &'static str = "roguelike-save";
+
+struct SavedActor {
+  map: String,
+  position: Point,
+  display_char: char,
+  name: String,
+  foreground: Color,
+  background: Color,
+  health: i32,
+  max_health: i32,
+  diet: Option<Diet>,
+  behavior: Behavior,
+  stats: Stats,
+  speed: i32,
+  energy: i32,
+  equipment: Vec<(Slot, String)>,
+  inventory: Vec<String>
+}
+
+struct SavedLevel {
+  depth: u32,
+  clock: u32,
+  tiles: Vec<(i32, String)>,
+  explored: Vec<(i32, String)>,
+  actors: Vec<SavedActor>,
+  items: Vec<(Point, String)>,
+  bombs: Vec<(Point, u32, u16)>,
+  boomerangs: Vec<SavedBoomerang>
+}
+
+struct SavedBoomerang {
+  position: Point,
+  origin: Point,
+  turn_point: Point,
+  returning: bool,
+  item: String
+}
+
+struct SavedGame {
+  seed: u32,
+  rng_state: [u32; 4],
+  char_location: Point,
+  states: Vec<String>,
+  depth: u32,
+  levels: Vec<SavedLevel>,
+  messages: Vec<String>
+}
+
+pub fn save_game(game: &Game, path: &Path) -> Result<(), String> {
+  let mut lines = vec![format!("{} {}", SAVE_HEADER, SAVE_VERSION)];
+
+  {
+    let move_info = game.move_info();
+    let move_info = move_info.borrow();
+    let rng = &move_info.deref().rng;
+    let state = rng.state();
+    lines.push(format!("seed {}", rng.seed()));
+    lines.push(format!("rng {} {} {} {}", state[0], state[1], state[2], state[3]));
+    lines.push(format!("player {} {}", move_info.char_location.x, move_info.char_location.y));
+  }
+
+  for state in game.states.iter() {
+    lines.push(format!("state {}", state.save_tag()));
+  }
+  lines.push(format!("depth {}", game.maps.depth));
+
+  let mut levels: Vec<&Maps> = game.other_levels.values().collect();
+  levels.push(&game.maps);
+  levels.sort_by_key(|level| level.depth);
+  for level in levels.iter() {
+    save_level(&mut lines, level);
+  }
+
+  for message in game.windows.messages.get_messages().iter() {
+    lines.push(format!("message {}", message));
+  }
+
+  let mut contents = lines.join("\n");
+  contents.push('\n');
+  match File::create(path).and_then(|mut f| f.write_all(contents.as_bytes())) {
+    Ok(_) => Ok(()),
+    Err(e) => Err(format!("Could not save to {}: {}", path.display(), e))
+  }
+}
+
+fn save_level(lines: &mut Vec<String>, level: &Maps) {
+  lines.push(format!("level {}", level.depth));
+  lines.push(format!("clock {}", level.clock));
+
+  let terrain = level.terrain.borrow();
+  for y in 0..terrain.size.max.y {
+    let row: String = (0..terrain.size.max.x).map(|x| terrain.tile_at(Point::new(x, y)).display_char()).collect();
+    lines.push(format!("tiles {} {}", y, row));
+  }
+  for y in 0..terrain.size.max.y {
+    let row: String = (0..terrain.size.max.x).map(|x| if level.fov.is_explored(Point::new(x, y)) { '1' } else { '0' }).collect();
+    lines.push(format!("explored {} {}", y, row));
+  }
+
+  save_actors(lines, "pcs", &level.pcs);
+  save_actors(lines, "friends", &level.friends);
+  save_actors(lines, "enemies", &level.enemies);
+
+  for &(point, ref item) in level.items.all().iter() {
+    lines.push(format!("item {} {} {}", point.x, point.y, item.name));
+  }
+  for bomb in level.bombs.iter() {
+    lines.push(format!("bomb {} {} {} {}", bomb.position.x, bomb.position.y, bomb.fuse, bomb.damage));
+  }
+  for boomerang in level.boomerangs.iter() {
+    lines.push(format!("boomerang {} {} {} {} {} {} {} {}",
+                       boomerang.position.x,
+                       boomerang.position.y,
+                       boomerang.origin.x,
+                       boomerang.origin.y,
+                       boomerang.turn_point.x,
+                       boomerang.turn_point.y,
+                       if boomerang.returning { 1 } else { 0 },
+                       boomerang.item.name));
+  }
+}
+
+fn save_actors(lines: &mut Vec<String>, map_name: &str, map: &Map) {
+  for actor in map.actors() {
+    lines.push(format!("actor {} {} {} {} {} {} {} {} {} {} {}",
+                       map_name,
+                       actor.position.x,
+                       actor.position.y,
+                       actor.display_char as u32,
+                       actor.foreground.name(),
+                       actor.background.name(),
+                       actor.health,
+                       actor.max_health,
+                       actor.diet.map(|diet| diet.name()).unwrap_or("none"),
+                       actor.behavior().name(),
+                       actor.name));
+    lines.push(format!("stats {} {} {} {}", actor.stats.accuracy, actor.stats.evasion, actor.stats.strength, actor.stats.defense));
+    lines.push(format!("speed {} {}", actor.speed, actor.energy));
+    for slot in Slot::all().iter() {
+      if let Some(item) = actor.equipment.get(*slot) {
+        lines.push(format!("equip {} {}", slot.name(), item.name));
+      }
+    }
+    for item in actor.inventory.items().iter() {
+      lines.push(format!("carry {}", item.name));
+    }
+  }
+}
+
+pub fn load_game(path: &Path, rc: Box<RenderingComponent>) -> Result<Game, String> {
+  let mut game = Game::new_without_actors(rc, 0);
+  // Whatever the game had to say while starting up, e.g. about broken
+  // definition files, goes after the saved messages
+  let startup = game.windows.messages.get_messages();
+  load_into(&mut game, path)?;
+  for message in startup.iter().rev() {
+    game.windows.messages.buffer_message(message);
+  }
+  Ok(game)
+}
+
+// Swaps the run `game` has going, if any, for the saved one
+pub fn load_into(game: &mut Game, path: &Path) -> Result<(), String> {
+  let mut contents = String::new();
+  if let Err(e) = File::open(path).and_then(|mut f| f.read_to_string(&mut contents)) {
+    return Err(format!("Could not load {}: {}", path.display(), e));
+  }
+  let saved = parse_save(&contents)?;
+
+  game.reset(saved.seed);
+  let move_info = game.move_info();
+  {
+    let mut mi = move_info.borrow_mut();
+    let mi = mi.deref_mut();
+    mi.rng = GameRng::new_with_state(saved.seed, saved.rng_state);
+    mi.char_location = saved.char_location;
+  }
+
+  let mut current = None;
+  for saved_level in saved.levels.into_iter() {
+    let mut level = Maps::new_level(move_info.clone(), saved_level.depth, game.fov_radius());
+    load_level(&mut level, saved_level, &move_info, &game.items)?;
+    if level.depth == saved.depth {
+      current = Some(level);
+    } else {
+      game.other_levels.insert(level.depth, level);
+    }
+  }
+  match current {
+    Some(level) => {
+      level.make_current();
+      game.maps = level;
+    },
+    None => { return Err(format!("Save file has no level at depth {}", saved.depth)); }
+  }
+
+  *game.windows.messages.get_mut_messages() = saved.messages.into_iter().map(Box::new).collect();
+
+  for tag in saved.states.iter() {
+    game.states.push(load_game_state(tag, &game.items)?);
+  }
+  if game.states.is_empty() {
+    game.states.push(Box::new(MovementGameState::new()));
+  }
+  if let Some(state) = game.states.last() {
+    state.enter(&mut game.windows);
+  }
+  game.game_over = game.maps.player_is_dead();
+  game.refresh_stats();
+
+  Ok(())
+}
+
+fn load_level(level: &mut Maps, saved: SavedLevel, move_info: &Rc<RefCell<MoveInfo>>, items: &ItemRegistry) -> Result<(), String> {
+  {
+    let mut terrain = level.terrain.borrow_mut();
+    for &(y, ref row) in saved.tiles.iter() {
+      for (x, c) in row.chars().enumerate() {
+        match Tile::from_display_char(c) {
+          Some(tile) => terrain.set_tile(Point::new(x as i32, y), tile),
+          None => { return Err(format!("Unknown tile '{}' in save file", c)); }
+        }
+      }
+    }
+  }
+
+  level.clock = saved.clock;
+
+  for &(y, ref row) in saved.explored.iter() {
+    for (x, c) in row.chars().enumerate() {
+      if c == '1' {
+        level.fov.set_explored(Point::new(x as i32, y));
+      }
+    }
+  }
+
+  for saved_actor in saved.actors.into_iter() {
+    let mc = saved_actor.behavior.movement_component(move_info.clone());
+    let position = saved_actor.position;
+    let is_pc = saved_actor.map == "pcs";
+    let mut actor = Box::new(Actor::new(position.x,
+                                    position.y,
+                                    saved_actor.display_char,
+                                    &saved_actor.name,
+                                    mc,
+                                    is_pc,
+                                    saved_actor.foreground,
+                                    saved_actor.background,
+                                    saved_actor.health));
+    actor.max_health = saved_actor.max_health;
+    actor.diet = saved_actor.diet;
+    actor.stats = saved_actor.stats;
+    actor.speed = saved_actor.speed;
+    actor.energy = saved_actor.energy;
+    for &(slot, ref item_name) in saved_actor.equipment.iter() {
+      match items.create(item_name) {
+        Some(item) => { actor.equipment.equip(slot, item); },
+        None => { return Err(format!("Unknown item '{}' in save file", item_name)); }
+      }
+    }
+    for item_name in saved_actor.inventory.iter() {
+      match items.create(item_name) {
+        Some(item) => { let _ = actor.inventory.add(item); },
+        None => { return Err(format!("Unknown item '{}' in save file", item_name)); }
+      }
+    }
+    match &saved_actor.map[..] {
+      "pcs" => level.pcs.push_actor(position, actor),
+      "friends" => level.friends.push_actor(position, actor),
+      _ => level.enemies.push_actor(position, actor),
+    };
+  }
+
+  for &(point, ref item_name) in saved.items.iter() {
+    match items.create(item_name) {
+      Some(item) => level.items.put(point, item),
+      None => { return Err(format!("Unknown item '{}' in save file", item_name)); }
+    }
+  }
+
+  for &(point, fuse, damage) in saved.bombs.iter() {
+    let mut bomb = LitBomb::new(point, damage);
+    bomb.fuse = fuse;
+    level.bombs.push(bomb);
+  }
+
+  for boomerang in saved.boomerangs.iter() {
+    let item = match items.create(&boomerang.item) {
+      Some(item) => item,
+      None => { return Err(format!("Unknown item '{}' in save file", boomerang.item)); }
+    };
+    level.boomerangs.push(FlyingBoomerang::in_flight(item, boomerang.position, boomerang.origin,
+                                                     boomerang.turn_point, boomerang.returning));
+  }
+
+  Ok(())
+}
+
+pub fn load_game_state(tag: &str, items: &ItemRegistry) -> Result<Box<GameState>, String> {
+  let mut parts = tag.splitn(2, ' ');
+  match (parts.next(), parts.next()) {
+    (Some("movement"), None) => Ok(Box::new(MovementGameState::new())),
+    (Some("game_over"), None) => Ok(Box::new(GameOverGameState::new())),
+    (Some("pause"), None) => Ok(Box::new(PauseGameState::new())),
+    (Some("feed"), None) => Ok(Box::new(FeedGameState::new())),
+    (Some("throw"), Some(target)) => {
+      let fields: Vec<Option<i32>> = target.split(' ').map(|field| field.parse().ok()).collect();
+      match fields[..] {
+        [Some(x), Some(y)] => Ok(Box::new(ThrowGameState::new_with_target(Point::new(x, y)))),
+        _ => Err(format!("Bad throw target '{}' in save file", target))
+      }
+    },
+    (Some("inventory"), Some(action_name)) => {
+      match InventoryAction::from_name(action_name) {
+        Some(action) => Ok(Box::new(InventoryGameState::new_with_action(action))),
+        None => Err(format!("Unknown inventory action '{}' in save file", action_name))
+      }
+    },
+    (Some("attack_input"), Some(weapon_name)) => {
+      let fists = Fists::new();
+      let weapon = if weapon_name == fists.get_name() {
+        Some(Box::new(fists) as Box<Weapon>)
+      } else {
+        items.create(weapon_name).and_then(|item| item.weapon)
+      };
+      match weapon {
+        Some(weapon) => Ok(Box::new(AttackInputGameState::new_with_weapon(weapon))),
+        None => Err(format!("Unknown weapon '{}' in save file", weapon_name))
+      }
+    },
+    _ => Err(format!("Unknown game state '{}' in save file", tag))
+  }
+}
+
+fn parse_save(contents: &str) -> Result<SavedGame, String> {
+  let records = records(contents);
+  let mut records = records.iter();
+
+  match records.next() {
+    Some(header) if header.name == SAVE_HEADER && header.fields.len() == 1 => {
+      let version: u32 = header.field(0)?;
+      if version != SAVE_VERSION {
+        return Err(format!("Save file version {} is not supported (expected {})", version, SAVE_VERSION));
+      }
+    },
+    _ => { return Err(String::from("Not a roguelike save file")); }
+  }
+
+  let mut saved = SavedGame {
+    seed: 0,
+    rng_state: [0u32; 4],
+    char_location: Point::new(0, 0),
+    states: vec![],
+    depth: 1,
+    levels: vec![],
+    messages: vec![]
+  };
+
+  for record in records {
+    match record.name {
+      "seed" => saved.seed = record.field(0)?,
+      "rng" => {
+        for i in 0..4 {
+          saved.rng_state[i] = record.field(i)?;
+        }
+      },
+      "player" => saved.char_location = Point::new(record.field(0)?, record.field(1)?),
+      "state" => saved.states.push(String::from(record.rest)),
+      "depth" => saved.depth = record.field(0)?,
+      "level" => {
+        saved.levels.push(SavedLevel {
+          depth: record.field(0)?,
+          clock: 0,
+          tiles: vec![],
+          explored: vec![],
+          actors: vec![],
+          items: vec![],
+          bombs: vec![],
+          boomerangs: vec![]
+        });
+      },
+      "tiles" => {
+        let y = record.field(0)?;
+        current_level(&mut saved)?.tiles.push((y, String::from(*record.fields.get(1).unwrap_or(&""))));
+      },
+      "explored" => {
+        let y = record.field(0)?;
+        current_level(&mut saved)?.explored.push((y, String::from(*record.fields.get(1).unwrap_or(&""))));
+      },
+      "actor" => {
+        let actor = parse_actor(record)?;
+        current_level(&mut saved)?.actors.push(actor);
+      },
+      "stats" => {
+        let stats = Stats::new(record.field(0)?, record.field(1)?, record.field(2)?, record.field(3)?);
+        match current_level(&mut saved)?.actors.last_mut() {
+          Some(actor) => actor.stats = stats,
+          None => { return Err(String::from("Stats record before any actor in save file")); }
+        }
+      },
+      "speed" => {
+        let speed = record.field(0)?;
+        let energy = record.field(1)?;
+        match current_level(&mut saved)?.actors.last_mut() {
+          Some(actor) => {
+            // Time would never come back round to a heroine who can't act
+            if speed < 0 || (speed == 0 && actor.map == "pcs") {
+              return Err(format!("Bad speed on {}", record.at));
+            }
+            actor.speed = speed;
+            actor.energy = energy;
+          },
+          None => { return Err(String::from("Speed record before any actor in save file")); }
+        }
+      },
+      "clock" => current_level(&mut saved)?.clock = record.field(0)?,
+      "equip" => {
+        let slot = record.named(0, Slot::from_name)?;
+        if record.fields.len() < 2 {
+          return Err(format!("Equip record on {} is too short", record.at));
+        }
+        let item_name = record.fields[1..].join(" ");
+        match current_level(&mut saved)?.actors.last_mut() {
+          Some(actor) => actor.equipment.push((slot, item_name)),
+          None => { return Err(String::from("Equip record before any actor in save file")); }
+        }
+      },
+      "carry" => {
+        match current_level(&mut saved)?.actors.last_mut() {
+          Some(actor) => actor.inventory.push(String::from(record.rest)),
+          None => { return Err(String::from("Carry record before any actor in save file")); }
+        }
+      },
+      "item" => {
+        if record.fields.len() < 3 {
+          return Err(format!("Item record on {} is too short", record.at));
+        }
+        let point = Point::new(record.field(0)?, record.field(1)?);
+        current_level(&mut saved)?.items.push((point, record.fields[2..].join(" ")));
+      },
+      "bomb" => {
+        let point = Point::new(record.field(0)?, record.field(1)?);
+        let fuse: u32 = record.field(2)?;
+        if fuse == 0 {
+          return Err(String::from("Bomb in save file has already gone off"));
+        }
+        let damage = record.field(3)?;
+        current_level(&mut saved)?.bombs.push((point, fuse, damage));
+      },
+      "boomerang" => {
+        if record.fields.len() < 8 {
+          return Err(format!("Boomerang record on {} is too short", record.at));
+        }
+        let boomerang = SavedBoomerang {
+          position: Point::new(record.field(0)?, record.field(1)?),
+          origin: Point::new(record.field(2)?, record.field(3)?),
+          turn_point: Point::new(record.field(4)?, record.field(5)?),
+          returning: record.field::<u8>(6)? == 1,
+          item: record.fields[7..].join(" ")
+        };
+        current_level(&mut saved)?.boomerangs.push(boomerang);
+      },
+      "message" => saved.messages.push(String::from(record.rest)),
+      _ => { return Err(format!("Unknown record '{}' in save file", record.name)); }
+    }
+  }
+
+  Ok(saved)
+}
+
+fn current_level(saved: &mut SavedGame) -> Result<&mut SavedLevel, String> {
+  match saved.levels.last_mut() {
+    Some(level) => Ok(level),
+    None => Err(String::from("Level record missing in save file"))
+  }
+}
+
+fn parse_actor(record: &Record) -> Result<SavedActor, String> {
+  if record.fields.len() < 11 {
+    return Err(format!("Actor record on {} is too short", record.at));
+  }
+  let glyph: u32 = record.field(3)?;
+  Ok(SavedActor {
+    map: match record.fields[0] {
+      "pcs" | "friends" | "enemies" => String::from(record.fields[0]),
+      _ => { return Err(format!("Bad actor map on {}", record.at)); }
+    },
+    position: Point::new(record.field(1)?, record.field(2)?),
+    display_char: ::std::char::from_u32(glyph).ok_or(format!("Bad actor glyph on {}", record.at))?,
+    foreground: record.named(4, Color::from_name)?,
+    background: record.named(5, Color::from_name)?,
+    health: record.field(6)?,
+    max_health: record.field(7)?,
+    diet: match record.fields[8] {
+      "none" => None,
+      _ => Some(record.named(8, Diet::from_name)?)
+    },
+    behavior: record.named(9, Behavior::from_name)?,
+    // The name is last so it can contain spaces
+    name: record.fields[10..].join(" "),
+    stats: Stats::new(0, 0, 0, 0),
+    speed: NORMAL_SPEED,
+    energy: TURN_ENERGY,
+    equipment: vec![],
+    inventory: vec![]
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const HEADER: &'static str = "roguelike-save 12\nseed 7\nrng 1 2 3 4\nplayer 40 25\nstate movement\nlevel 1\n";
+
+  fn error(contents: &str) -> String {
+    match parse_save(contents) {
+      Ok(_) => panic!("loaded a broken save:\n{}", contents),
+      Err(e) => e
+    }
+  }
+
+  #[test]
+  fn loads_a_minimal_save() {
+    let contents = format!("{}actor pcs 40 25 64 blue black 20 20 none user the heroine\nstats 2 2 1 0\nmessage Hello there\n", HEADER);
+    let saved = parse_save(&contents).unwrap();
+    assert_eq!(saved.seed, 7);
+    assert_eq!(saved.rng_state, [1, 2, 3, 4]);
+    assert_eq!(saved.states, vec!["movement"]);
+    assert_eq!(saved.levels[0].actors[0].display_char, '@');
+    assert_eq!(saved.levels[0].actors[0].health, 20);
+    assert_eq!(saved.levels[0].actors[0].name, "the heroine");
+    assert_eq!(saved.levels[0].actors[0].stats.defense, 0);
+    assert_eq!(saved.messages, vec!["Hello there"]);
+  }
+
+  #[test]
+  fn version_mismatch() {
+    assert_eq!(error("roguelike-save 2\nseed 7\n"), "Save file version 2 is not supported (expected 12)");
+    // Saves from before actors had stats, speed and the rest are turned
+    // away instead of misread
+    assert_eq!(error(&HEADER.replace("roguelike-save 12", "roguelike-save 1")), "Save file version 1 is not supported (expected 12)");
+    assert_eq!(error("roguelike-save\nseed 7\n"), "Not a roguelike save file");
+    assert_eq!(error(""), "Not a roguelike save file");
+    assert_eq!(error("seed 7\n"), "Not a roguelike save file");
+  }
+
+  #[test]
+  fn truncated_records() {
+    assert_eq!(error(&format!("{}actor pcs 40 25 64 blue bl", HEADER)), "Actor record on line 7 is too short");
+    assert_eq!(error(&format!("{}actor pcs 40 25 64 blue black 20 20 none user heroine\nstats 2 2", HEADER)), "Bad number on line 8");
+    assert_eq!(error(&format!("{}bomb 40 25 2", HEADER)), "Bad number on line 7");
+    assert_eq!(error(&format!("{}boomerang 40 25 40", HEADER)), "Boomerang record on line 7 is too short");
+    assert_eq!(error(&format!("{}equip main_hand", HEADER)), "Equip record on line 7 is too short");
+    assert_eq!(error(&format!("{}item 40", HEADER)), "Item record on line 7 is too short");
+    assert_eq!(error("roguelike-save 12\nrng 1 2"), "Bad number on line 2");
+    assert_eq!(error("roguelike-save 12\nplayer"), "Bad number on line 2");
+  }
+
+  #[test]
+  fn malformed_records() {
+    assert_eq!(error(&format!("{}actor pcs 40 25 64 mauve black 20 20 none user heroine", HEADER)), "Bad value 'mauve' on line 7");
+    assert_eq!(error(&format!("{}actor pets 40 25 64 blue black 20 20 none user heroine", HEADER)), "Bad actor map on line 7");
+    assert_eq!(error(&format!("{}bomb 40 25 0 12", HEADER)), "Bomb in save file has already gone off");
+    assert_eq!(error(&format!("{}actor pcs 40 25 64 blue black 20 20 none user heroine\nspeed 0 100", HEADER)), "Bad speed on line 8");
+    assert_eq!(error(&format!("{}actor enemies 41 25 107 red black 5 5 none aggro kobold\nspeed -5 100", HEADER)), "Bad speed on line 8");
+    assert_eq!(error("roguelike-save 12\nstats 1 2 3 4"), "Level record missing in save file");
+    assert_eq!(error("roguelike-save 12\nlevel 1\nstats 1 2 3 4"), "Stats record before any actor in save file");
+    assert_eq!(error("roguelike-save 12\nteleport 3 4"), "Unknown record 'teleport' in save file");
+  }
+}
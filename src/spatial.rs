@@ -0,0 +1,79 @@
+use actor::ActorId;
+use util::{Point, Bound};
+
+// Which actors stand on each tile of a map, so looking up a position does
+// not mean walking every actor. Cells are allocated once up front.
+pub struct SpatialIndex {
+    size: Bound,
+    cells: Vec<Vec<ActorId>>
+}
+
+impl SpatialIndex {
+    pub fn new(size: Bound) -> SpatialIndex {
+        SpatialIndex {
+            size: size,
+            cells: vec![vec![]; size.cell_count()]
+        }
+    }
+
+    pub fn insert(&mut self, id: ActorId, point: Point) {
+        if let Some(cell) = self.size.cell_index(point) {
+            self.cells[cell].push(id);
+        }
+    }
+
+    pub fn remove(&mut self, id: ActorId, point: Point) {
+        if let Some(cell) = self.size.cell_index(point) {
+            self.cells[cell].retain(|&other| other != id);
+        }
+    }
+
+    pub fn move_actor(&mut self, id: ActorId, from: Point, to: Point) {
+        self.remove(id, from);
+        self.insert(id, to);
+    }
+
+    pub fn at(&self, point: Point) -> &[ActorId] {
+        match self.size.cell_index(point) {
+            Some(cell) => &self.cells[cell],
+            None => &[]
+        }
+    }
+}
+
+// How many actors, of any kind, stand on each tile. Shared through the
+// move info so movement components can tell which tiles are taken.
+pub struct Occupancy {
+    size: Bound,
+    counts: Vec<u16>
+}
+
+impl Occupancy {
+    pub fn new(size: Bound) -> Occupancy {
+        Occupancy {
+            size: size,
+            counts: vec![0; size.cell_count()]
+        }
+    }
+
+    pub fn enter(&mut self, point: Point) {
+        if let Some(cell) = self.size.cell_index(point) {
+            self.counts[cell] += 1;
+        }
+    }
+
+    pub fn leave(&mut self, point: Point) {
+        if let Some(cell) = self.size.cell_index(point) {
+            if self.counts[cell] > 0 {
+                self.counts[cell] -= 1;
+            }
+        }
+    }
+
+    pub fn is_occupied(&self, point: Point) -> bool {
+        match self.size.cell_index(point) {
+            Some(cell) => self.counts[cell] > 0,
+            None => false
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use rendering::renderers::{RenderingComponent, Color};
+use util::{Point, Bound};
+use fov::FieldOfView;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Tile {
+    Floor,
+    Wall,
+    StairsUp,
+    StairsDown
+}
+
+impl Tile {
+    pub fn blocks_movement(&self) -> bool {
+        match *self {
+            Tile::Wall => true,
+            _ => false
+        }
+    }
+
+    pub fn blocks_sight(&self) -> bool {
+        match *self {
+            Tile::Wall => true,
+            _ => false
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Tile::Floor => "floor",
+            Tile::Wall => "wall",
+            Tile::StairsUp => "staircase up",
+            Tile::StairsDown => "staircase down"
+        }
+    }
+
+    pub fn display_char(&self) -> char {
+        match *self {
+            Tile::Floor => '.',
+            Tile::Wall => '#',
+            Tile::StairsUp => '<',
+            Tile::StairsDown => '>'
+        }
+    }
+
+    pub fn from_display_char(c: char) -> Option<Tile> {
+        match c {
+            '.' => Some(Tile::Floor),
+            '#' => Some(Tile::Wall),
+            '<' => Some(Tile::StairsUp),
+            '>' => Some(Tile::StairsDown),
+            _ => None
+        }
+    }
+
+    pub fn foreground(&self) -> Color {
+        match *self {
+            Tile::Floor => Color::Grey,
+            _ => Color::White
+        }
+    }
+
+    pub fn background(&self) -> Color {
+        Color::Black
+    }
+}
+
+// The tiles the actors stand on. Anything outside the grid counts as wall.
+pub struct Terrain {
+    pub size: Bound,
+    tiles: Vec<Tile>
+}
+
+impl Terrain {
+    pub fn new(size: Bound) -> Terrain {
+        Terrain {
+            size: size,
+            tiles: vec![Tile::Floor; size.cell_count()]
+        }
+    }
+
+    pub fn fill(&mut self, tile: Tile) {
+        for t in self.tiles.iter_mut() {
+            *t = tile;
+        }
+    }
+
+    pub fn tile_at(&self, point: Point) -> Tile {
+        match self.size.cell_index(point) {
+            Some(cell) => self.tiles[cell],
+            None => Tile::Wall
+        }
+    }
+
+    pub fn set_tile(&mut self, point: Point, tile: Tile) {
+        if let Some(cell) = self.size.cell_index(point) {
+            self.tiles[cell] = tile;
+        }
+    }
+
+    pub fn find_tile(&self, tile: Tile) -> Option<Point> {
+        for x in self.size.min.x..self.size.max.x {
+            for y in self.size.min.y..self.size.max.y {
+                let point = Point::new(x, y);
+                if self.tile_at(point) == tile {
+                    return Some(point);
+                }
+            }
+        }
+        None
+    }
+
+    pub fn is_blocked(&self, point: Point) -> bool {
+        self.tile_at(point).blocks_movement()
+    }
+
+    // Tiles in view are drawn as they are, remembered ones dimmed and the
+    // rest not at all
+    pub fn render(&self, renderer: &mut Box<RenderingComponent>, fov: &FieldOfView) {
+        for x in self.size.min.x..self.size.max.x {
+            for y in self.size.min.y..self.size.max.y {
+                let point = Point::new(x, y);
+                let tile = self.tile_at(point);
+                if fov.is_visible(point) {
+                    renderer.render_object(point, tile.display_char(), tile.foreground(), tile.background());
+                } else if fov.is_explored(point) {
+                    renderer.render_object(point, tile.display_char(), Color::DarkGrey, tile.background());
+                }
+            }
+        }
+    }
+}
@@ -15,7 +15,7 @@ pub enum PointEquality {
     PointsNotEqual
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Point {
   pub x: i32,
   pub y: i32
@@ -86,6 +86,13 @@ impl Bound {
         }
     }
 
+    pub fn center(&self) -> Point {
+        Point {
+            x: (self.min.x + self.max.x) / 2,
+            y: (self.min.y + self.max.y) / 2
+        }
+    }
+
     pub fn contains(&self, point: Point) -> Contains {
         if
             point.x >= self.min.x &&
@@ -98,4 +105,47 @@ impl Bound {
             Contains::DoesNotContain
         }
     }
-}
\ No newline at end of file
+
+    pub fn in_bounds(&self, point: Point) -> bool {
+        self.cell_index(point).is_some()
+    }
+
+    // Grids over a bound, like the terrain, keep their cells in one Vec,
+    // column by column
+    pub fn cell_index(&self, point: Point) -> Option<usize> {
+        match self.contains(point) {
+            Contains::DoesContain => Some(((point.x - self.min.x) * (self.max.y - self.min.y) + point.y - self.min.y) as usize),
+            Contains::DoesNotContain => None
+        }
+    }
+
+    pub fn cell_count(&self) -> usize {
+        ((self.max.x - self.min.x) * (self.max.y - self.min.y)) as usize
+    }
+}
+
+// Every point on the straight line from `from` to `to`, both ends included
+// (Bresenham's algorithm)
+pub fn line(from: Point, to: Point) -> Vec<Point> {
+    let dx = (to.x - from.x).abs();
+    let dy = -(to.y - from.y).abs();
+    let step_x = if from.x < to.x { 1 } else { -1 };
+    let step_y = if from.y < to.y { 1 } else { -1 };
+    let mut error = dx + dy;
+    let mut current = from;
+    let mut points = vec![current];
+
+    while current.x != to.x || current.y != to.y {
+        let doubled = error * 2;
+        if doubled >= dy {
+            error += dy;
+            current = current.offset_x(step_x);
+        }
+        if doubled <= dx {
+            error += dx;
+            current = current.offset_y(step_y);
+        }
+        points.push(current);
+    }
+    points
+}
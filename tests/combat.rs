@@ -0,0 +1,27 @@
+extern crate roguelike;
+
+mod common;
+
+use common::{from_save, messages};
+use roguelike::util::Point;
+
+const HIT: &'static str = "The Little Boomerang hits the kobold for ";
+
+#[test]
+fn damage_wears_enemies_down_until_they_die() {
+    // The boomerang does 6 to 13, so the kobold on the right survives a hit
+    // and the one on the left doesn't. Neither ever moves.
+    let records = "player 40 25\nstate attack_input Little Boomerang\nlevel 1\n\
+                   actor pcs 40 25 64 blue black 20 20 none user heroine\n\
+                   equip main_hand Little Boomerang\n\
+                   actor enemies 41 25 107 red black 20 20 none random kobold\nspeed 0 0\n\
+                   actor enemies 39 25 107 red black 6 6 none random kobold\nspeed 0 0\n";
+    let hurt = from_save("damage-hurt", records, "right");
+    let hit = messages(&hurt.game).into_iter().find(|m| m.starts_with(HIT)).unwrap();
+    let damage: i32 = hit[HIT.len()..].split(' ').next().unwrap().parse().unwrap();
+    assert_eq!(hurt.game.maps.enemy_at(Point::new(41, 25)).unwrap().health, 20 - damage);
+
+    let killed = from_save("damage-killed", records, "left");
+    assert!(messages(&killed.game).iter().any(|m| m == "You killed the kobold!"));
+    assert!(killed.game.maps.enemy_at(Point::new(39, 25)).is_none());
+}
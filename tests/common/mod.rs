@@ -0,0 +1,98 @@
+// Helpers shared by the integration tests; not every test uses all of them
+#![allow(dead_code)]
+
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+use roguelike::game::Game;
+use roguelike::input::{InputSource, ScriptedInputSource};
+use roguelike::rendering::renderers::{HeadlessRenderingComponent, RenderingComponent};
+use roguelike::rng::GameRng;
+use roguelike::save;
+
+// Everything that was drawn while a script played out, frame by frame
+pub struct Session {
+    pub game: Game,
+    pub frames: Vec<String>,
+    pub stats: Vec<Vec<String>>
+}
+
+pub fn renderer(script: &str) -> Box<RenderingComponent> {
+    let source: Box<InputSource> = Box::new(ScriptedInputSource::from_script(script).unwrap());
+    Box::new(HeadlessRenderingComponent::new_with_input_source(Game::total_bounds(), source))
+}
+
+// The same loop as `Game::run`, keeping every frame
+pub fn play(mut game: Game) -> Session {
+    let mut frames = vec![];
+    let mut stats = vec![];
+    game.render();
+    loop {
+        frames.push(game.rendering_component.frame_text().unwrap());
+        stats.push(game.windows.stats.get_messages().iter().map(|line| line.to_string()).collect());
+        if game.rendering_component.window_closed() || game.exit {
+            break;
+        }
+        game.wait_for_keypress();
+        game.update();
+        game.render();
+    }
+    Session { game: game, frames: frames, stats: stats }
+}
+
+pub fn new_game(seed: u32, script: &str) -> Session {
+    play(Game::new_with_renderer(renderer(script), seed))
+}
+
+pub fn save_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("roguelike-test-{}.sav", name))
+}
+
+pub fn write_save(name: &str, records: &str) -> PathBuf {
+    write_save_with_rng(name, "seed 1\nrng 1 2 3 4", records)
+}
+
+fn write_save_with_rng(name: &str, rng: &str, records: &str) -> PathBuf {
+    let path = save_path(name);
+    let contents = format!("roguelike-save {}\n{}\n{}", save::SAVE_VERSION, rng, records);
+    File::create(&path).and_then(|mut f| f.write_all(contents.as_bytes())).unwrap();
+    path
+}
+
+// Starts from a hand written save, so the scene doesn't depend on where the
+// game put everything
+pub fn from_save(name: &str, records: &str, script: &str) -> Session {
+    let path = write_save(name, records);
+    play(save::load_game(&path, renderer(script)).unwrap())
+}
+
+// The same, with the random numbers starting where `seed` would start them
+pub fn from_save_with_seed(name: &str, seed: u32, records: &str, script: &str) -> Session {
+    let state = GameRng::new(seed).state();
+    let rng = format!("seed {}\nrng {} {} {} {}", seed, state[0], state[1], state[2], state[3]);
+    let path = write_save_with_rng(name, &rng, records);
+    play(save::load_game(&path, renderer(script)).unwrap())
+}
+
+pub fn messages(game: &Game) -> Vec<String> {
+    game.windows.messages.get_messages().iter().map(|message| message.to_string()).collect()
+}
+
+pub fn heroine_at(game: &Game) -> (i32, i32) {
+    let location = game.move_info().borrow().char_location;
+    (location.x, location.y)
+}
+
+pub fn assert_same(a: &Session, b: &Session) {
+    assert_eq!(a.frames.len(), b.frames.len());
+    for (index, (x, y)) in a.frames.iter().zip(b.frames.iter()).enumerate() {
+        assert!(x == y, "frame {} differs:\n{}\n---\n{}", index, x, y);
+    }
+    assert_eq!(a.stats, b.stats);
+}
+
+// The heroine alone at (40, 25)
+pub const HEROINE: &'static str = "player 40 25\nstate movement\nlevel 1\n\
+                                   actor pcs 40 25 64 blue black 20 20 none user heroine\n";
@@ -0,0 +1,69 @@
+extern crate roguelike;
+
+mod common;
+
+use roguelike::game::Game;
+use roguelike::input::{InputSource, ScriptedInputSource};
+use roguelike::rendering::renderers::{HeadlessRenderingComponent, RenderingComponent};
+use roguelike::terrain::Tile;
+use roguelike::util::Point;
+
+fn play(seed: u32, script: &str) -> Game {
+    let source: Box<InputSource> = Box::new(ScriptedInputSource::from_script(script).unwrap());
+    let rc: Box<RenderingComponent> = Box::new(HeadlessRenderingComponent::new_with_input_source(Game::total_bounds(), source));
+    let mut game = Game::new_with_renderer(rc, seed);
+    game.run();
+    game
+}
+
+#[test]
+fn game_loop_renders_a_text_frame() {
+    let game = play(42, "up,left,down");
+    let frame = game.rendering_component.frame_text().unwrap();
+    let lines: Vec<&str> = frame.lines().collect();
+
+    assert_eq!(lines.len(), 62);
+    assert!(lines.iter().all(|line| line.chars().count() == 100));
+    assert!(frame.contains("Seed: 42"));
+    assert!(frame.contains("Depth: 1"));
+    assert!(frame.contains("Wielding: Heroic"));
+
+    // The heroine is drawn on the map where she stands
+    let location = game.move_info().borrow().char_location;
+    let row: Vec<char> = lines[location.y as usize].chars().collect();
+    assert_eq!(row[location.x as usize], '@');
+}
+
+#[test]
+fn frames_follow_the_heroine() {
+    let before = play(42, "none");
+    let after = play(42, "none,right");
+    let from = before.move_info().borrow().char_location;
+    let to = after.move_info().borrow().char_location;
+
+    let frame = after.rendering_component.frame_text().unwrap();
+    let row: Vec<char> = frame.lines().nth(to.y as usize).unwrap().chars().collect();
+    assert_eq!(row[to.x as usize], '@');
+    assert_eq!(to.x, from.x + 1);
+    let row: Vec<char> = frame.lines().nth(from.y as usize).unwrap().chars().collect();
+    assert!(row[from.x as usize] != '@');
+}
+
+#[test]
+fn sight_radius_can_be_changed() {
+    let records = format!("{}tiles 25 {}>\n", common::HEROINE, ".".repeat(40));
+    let mut game = common::from_save("sight", &records, "").game;
+    let in_view = |game: &Game| {
+        (0..78).flat_map(|x| (0..49).map(move |y| Point::new(x, y))).filter(|&point| game.maps.fov.is_visible(point)).count()
+    };
+    let default = in_view(&game);
+    game.set_fov_radius(2);
+    assert_eq!(in_view(&game), 13);
+    game.set_fov_radius(12);
+    assert!(in_view(&game) > default);
+
+    // Levels made later are seen just as far
+    game.take_stairs(Tile::StairsDown);
+    assert_eq!(game.maps.depth, 2);
+    assert_eq!(game.maps.fov.radius, 12);
+}
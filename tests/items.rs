@@ -0,0 +1,202 @@
+extern crate roguelike;
+
+mod common;
+
+use common::{HEROINE, from_save, from_save_with_seed, messages, renderer, write_save};
+use roguelike::combat::{BOMB_FUSE, LitBomb};
+use roguelike::game::Game;
+use roguelike::item::{DEFAULT_CAPACITY, Slot};
+use roguelike::movement::TICKS_PER_TURN;
+use roguelike::save;
+use roguelike::util::Point;
+
+fn health(game: &Game, name: &str) -> i32 {
+    game.maps.enemies.actors().find(|actor| actor.name == name).unwrap().health
+}
+
+#[test]
+fn nothing_fits_in_a_full_pack() {
+    let records = format!("{}{}item 40 25 Bomb\n", HEROINE, "carry Lettuce\n".repeat(DEFAULT_CAPACITY));
+    let session = from_save("full-pack", &records, "g");
+    let game = &session.game;
+    assert_eq!(messages(game)[0], "Your pack is full.");
+    assert_eq!(floor_item(game, 40, 25), Some(String::from("Bomb")));
+    assert_eq!(game.maps.player().unwrap().inventory.len(), DEFAULT_CAPACITY);
+    assert_eq!(game.maps.clock, 0);
+}
+
+fn stats(game: &Game) -> Vec<String> {
+    game.windows.stats.get_messages().iter().map(|line| line.to_string()).collect()
+}
+
+// The heroine with her sword in the pack and a kobold in front of her that
+// is easy to hit and never moves
+fn unarmed(name: &str, script: &str) -> Game {
+    let records = format!("{}stats 2 2 1 0\ncarry Heroic Sworc\nactor enemies 41 25 107 red black 30 30 none random kobold\nstats 0 -20 0 0\nspeed 0 0\n",
+                          HEROINE);
+    from_save(name, &records, script).game
+}
+
+#[test]
+fn wielding_and_unwielding() {
+    let game = unarmed("wield", "w,1");
+    assert_eq!(messages(&game)[0], "You wield the Heroic Sworc.");
+    assert_eq!(wielding(&game), Some(String::from("Heroic Sworc")));
+    assert!(stats(&game).contains(&String::from("Wielding: Heroic Sworc")));
+    assert!(stats(&game).contains(&String::from("1) Main hand: Heroic Sworc")));
+    assert!(game.maps.player().unwrap().inventory.items().is_empty());
+
+    let game = unarmed("wield", "w,1,u,1");
+    assert_eq!(messages(&game)[0], "You put away the Heroic Sworc.");
+    assert_eq!(wielding(&game), None);
+    assert!(stats(&game).contains(&String::from("Wielding: nothing")));
+    assert!(stats(&game).contains(&String::from("1) Heroic Sworc")));
+}
+
+#[test]
+fn wielded_weapons_do_their_own_damage() {
+    // Bare hands do 1 or 2, plus her strength of 1; the sword 2d3+2 plus 1
+    let game = unarmed("punch", "right");
+    assert_eq!(messages(&game)[0], format!("You attack the kobold with your bare hands for {} points of damage!", 30 - health(&game, "kobold")));
+    assert!(health(&game, "kobold") >= 27);
+
+    let game = unarmed("slash", "w,1,right");
+    assert_eq!(messages(&game)[0], format!("You attack the kobold with your Heroic Sworc for {} points of damage!", 30 - health(&game, "kobold")));
+    assert!(health(&game, "kobold") <= 25);
+}
+
+// A bomb in hand and three goblins that never move, one, two and three
+// tiles from where it lands. Tests run in parallel, so each writes its own
+// save.
+fn bomb_scene(name: &str, script: &str) -> Game {
+    let records = format!("{}equip main_hand Bomb\n\
+                           actor enemies 44 26 103 green black 30 30 none random near\nspeed 0 0\n\
+                           actor enemies 46 25 103 green black 30 30 none random middle\nspeed 0 0\n\
+                           actor enemies 44 22 103 green black 30 30 none random far\nspeed 0 0\n",
+                          HEROINE);
+    from_save(name, &records, script).game
+}
+
+const THROW: &'static str = "a,right,right,right,right,a";
+
+#[test]
+fn bombs_go_off_after_the_fuse_burns_down() {
+    // The throw takes the heroine past one turn, every step after it one more
+    for steps in 0..BOMB_FUSE {
+        let script = format!("{}{}", THROW, ",left".repeat(steps as usize));
+        let game = bomb_scene("bomb-fuse", &script);
+        let turns = game.maps.clock / TICKS_PER_TURN;
+        if turns < BOMB_FUSE {
+            assert_eq!(game.maps.bombs.len(), 1);
+            assert!(game.maps.bombs[0].position == Point::new(44, 25));
+            assert_eq!(game.maps.bombs[0].fuse, BOMB_FUSE - turns);
+            assert!(!messages(&game).contains(&String::from("The bomb explodes!")));
+        } else {
+            assert_eq!(turns, BOMB_FUSE);
+            assert!(game.maps.bombs.is_empty());
+            assert!(messages(&game).contains(&String::from("The bomb explodes!")));
+        }
+    }
+}
+
+#[test]
+fn bomb_damage_falls_off_towards_the_edge() {
+    let script = format!("{}{}", THROW, ",left".repeat(BOMB_FUSE as usize));
+    let game = bomb_scene("bomb-damage", &script);
+    assert!(game.maps.bombs.is_empty());
+
+    // The Bomb's blast is a flat 12
+    let bomb = LitBomb::new(Point::new(44, 25), 12);
+    assert_eq!(health(&game, "near"), 30 - bomb.damage_at(1) as i32);
+    assert_eq!(health(&game, "middle"), 30 - bomb.damage_at(2) as i32);
+    assert_eq!(health(&game, "far"), 30);
+    assert!(bomb.damage_at(1) > bomb.damage_at(2));
+    assert_eq!(bomb.damage_at(3), 0);
+}
+
+#[test]
+fn saves_with_spent_bombs_are_rejected() {
+    let path = write_save("spent-bomb", &format!("{}bomb 42 25 0 12\n", HEROINE));
+    let error = save::load_game(&path, renderer("")).err().unwrap();
+    assert_eq!(error, "Bomb in save file has already gone off");
+}
+
+// A goat next to the heroine that never moves, and one lettuce to offer
+// it. Whether it calms down is the first roll after loading: 15 with seed
+// 0 and 72 with seed 3, against a TAME_CHANCE of 60.
+fn feed_goat(seed: u32) -> Game {
+    let records = format!("{}carry Lettuce\nactor enemies 41 25 103 grey black 15 15 herbivore aggro goat\nspeed 0 0\n", HEROINE);
+    from_save_with_seed(&format!("feed-{}", seed), seed, &records, "f,right").game
+}
+
+fn carries_lettuce(game: &Game) -> bool {
+    game.maps.player().unwrap().inventory.items().iter().any(|item| item.name == "Lettuce")
+}
+
+#[test]
+fn fed_animals_can_be_tamed() {
+    let game = feed_goat(0);
+    assert_eq!(messages(&game)[0], "The goat eats the Lettuce and calms down. It follows you now.");
+    assert_eq!(game.maps.enemies.len(), 0);
+    assert_eq!(game.maps.friends.actor_at(Point::new(41, 25)).map(|goat| goat.name.clone()), Some(String::from("goat")));
+    assert!(!carries_lettuce(&game));
+}
+
+#[test]
+fn failed_taming_leaves_the_animal_hostile() {
+    let game = feed_goat(3);
+    assert_eq!(messages(&game)[0], "The goat eats the Lettuce but is still angry.");
+    assert!(game.maps.friends.actor_at(Point::new(41, 25)).is_none());
+    assert_eq!(game.maps.enemies.actor_at(Point::new(41, 25)).map(|goat| goat.name.clone()), Some(String::from("goat")));
+    // The lettuce is eaten either way
+    assert!(!carries_lettuce(&game));
+}
+
+// The heroine with a boomerang in hand; it flies six tiles out and comes
+// back along the same line
+fn boomerang_scene(name: &str, extra: &str, script: &str) -> Game {
+    let records = format!("{}equip main_hand Little Boomerang\n{}", HEROINE, extra);
+    from_save(name, &records, script).game
+}
+
+fn floor_item(game: &Game, x: i32, y: i32) -> Option<String> {
+    game.maps.items.top_at(Point::new(x, y)).map(|item| item.name.clone())
+}
+
+fn wielding(game: &Game) -> Option<String> {
+    game.maps.player().unwrap().equipment.get(Slot::MainHand).map(|item| item.name.clone())
+}
+
+#[test]
+fn boomerangs_hit_and_come_back() {
+    // Easy to hit and hard to kill, and it stays put
+    let game = boomerang_scene("boomerang-hit", "actor enemies 43 25 107 green black 50 50 none random kobold\nstats 0 -20 0 0\nspeed 0 0\n", "a,right");
+    assert!(health(&game, "kobold") < 50);
+    assert!(game.maps.boomerangs.is_empty());
+    assert_eq!(messages(&game)[0], "You catch the Little Boomerang.");
+    assert_eq!(wielding(&game), Some(String::from("Little Boomerang")));
+}
+
+#[test]
+fn boomerangs_drop_when_they_hit_a_wall() {
+    let records = format!("tiles 25 {}#\n", ".".repeat(43));
+    let game = boomerang_scene("boomerang-wall", &records, "a,right");
+    assert!(game.maps.boomerangs.is_empty());
+    assert_eq!(messages(&game)[0], "The Little Boomerang hits the wall and drops.");
+    assert_eq!(floor_item(&game, 42, 25), Some(String::from("Little Boomerang")));
+    assert_eq!(wielding(&game), None);
+}
+
+#[test]
+fn boomerangs_drop_when_the_thrower_has_moved() {
+    let game = boomerang_scene("boomerang-moved", "", "a,right,down,down");
+    assert_eq!(game.maps.boomerangs.len(), 1);
+    assert!(game.maps.boomerangs[0].returning);
+
+    // Back where it was thrown from, with nobody there to catch it
+    let game = boomerang_scene("boomerang-moved", "", "a,right,down,down,down");
+    assert!(game.maps.boomerangs.is_empty());
+    assert_eq!(messages(&game)[0], "The Little Boomerang drops to the floor.");
+    assert_eq!(floor_item(&game, 40, 25), Some(String::from("Little Boomerang")));
+    assert_eq!(wielding(&game), None);
+}
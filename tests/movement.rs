@@ -0,0 +1,77 @@
+extern crate roguelike;
+
+mod common;
+
+use common::{HEROINE, from_save, heroine_at, messages};
+use roguelike::movement::{ITEM_COST, NORMAL_SPEED, REST_COST, TICKS_PER_TURN};
+use roguelike::util::Point;
+
+#[test]
+fn walking_into_a_friend_swaps_places() {
+    let records = format!("{}actor friends 41 25 100 white black 20 20 none follow dog\nspeed 0 0\n", HEROINE);
+    let session = from_save("swap", &records, "right");
+    let game = &session.game;
+    assert_eq!(heroine_at(game), (41, 25));
+    assert_eq!(game.maps.friends.actor_at(Point::new(40, 25)).map(|dog| dog.name.clone()), Some(String::from("dog")));
+    assert_eq!(messages(game)[0], "You swap places with the dog.");
+    // A step's worth of time, not an attack's
+    assert_eq!(game.maps.clock, TICKS_PER_TURN);
+}
+
+// The heroine moves at normal speed, so every key below is one turn. Each
+// step of the chase takes the fast one a column closer and the slow one a
+// row closer, whichever way they go around.
+fn chasers(script: &str) -> (i32, i32) {
+    let records = format!("{}actor enemies 70 25 102 red black 30 30 none aggro fast\nspeed 20 0\n\
+                           actor enemies 40 45 115 red black 30 30 none aggro slow\nspeed 5 0\n", HEROINE);
+    let session = from_save("speeds", &records, script);
+    let at = |name: &str| session.game.maps.enemies.actors().find(|actor| actor.name == name).unwrap().position;
+    (70 - at("fast").x, 45 - at("slow").y)
+}
+
+#[test]
+fn faster_actors_act_more_often() {
+    assert_eq!(chasers("left"), (2, 0));
+    assert_eq!(chasers("left,left"), (4, 1));
+    assert_eq!(chasers("left,left,left,left"), (8, 2));
+}
+
+#[test]
+fn actors_without_speed_never_act() {
+    let records = format!("{}actor enemies 42 25 107 red black 20 20 none aggro kobold\nspeed 0 0\n", HEROINE);
+    let session = from_save("statue", &records, &",z".repeat(30)[1..]);
+    assert_eq!(session.game.maps.clock, 30 * (REST_COST / NORMAL_SPEED) as u32);
+    assert_eq!(session.game.maps.enemies.actor_at(Point::new(42, 25)).map(|kobold| kobold.energy), Some(0));
+    assert!(messages(&session.game).is_empty());
+}
+
+#[test]
+fn bumping_into_a_wall_takes_no_time() {
+    let records = format!("{}tiles 25 {}#\n", HEROINE, ".".repeat(41));
+    let session = from_save("wall", &records, "right,right");
+    assert_eq!(heroine_at(&session.game), (40, 25));
+    assert_eq!(session.game.maps.clock, 0);
+    assert_eq!(messages(&session.game)[0], "There is a wall in the way.");
+}
+
+#[test]
+fn walking_off_the_map_takes_no_time() {
+    let records = HEROINE.replace("40 25", "0 25");
+    let session = from_save("edge", &records, "left");
+    assert_eq!(heroine_at(&session.game), (0, 25));
+    assert_eq!(session.game.maps.clock, 0);
+    assert_eq!(messages(&session.game)[0], "You can't move that way!");
+}
+
+#[test]
+fn failed_actions_take_no_time() {
+    let session = from_save("nothing", HEROINE, "g,d,5,w,1,u,9,a,up");
+    assert_eq!(session.game.maps.clock, 0);
+    assert_eq!(messages(&session.game)[0], "No enemy in that direction!");
+
+    // Whereas picking something up does
+    let records = format!("{}item 40 25 Lettuce\n", HEROINE);
+    let session = from_save("pick-up", &records, "g");
+    assert_eq!(messages(&session.game)[0], "You pick up the Lettuce.");
+    assert_eq!(session.game.maps.clock, (ITEM_COST / NORMAL_SPEED) as u32);
+}
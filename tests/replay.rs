@@ -0,0 +1,125 @@
+extern crate roguelike;
+
+mod common;
+
+use common::{HEROINE, assert_same, from_save, heroine_at, messages, new_game, play, renderer, save_path};
+use roguelike::input::{KeyboardInput, GameKey, GameKeyCode, InputSource, ScriptedInputSource};
+use roguelike::save;
+
+fn keys(script: &str) -> Vec<GameKey> {
+    let mut source = ScriptedInputSource::from_script(script).unwrap();
+    let mut keys = vec![];
+    while let Some(keypress) = source.next_keypress() {
+        keys.push(keypress.key);
+    }
+    assert!(source.is_exhausted());
+    keys
+}
+
+#[test]
+fn scripts_are_split_on_commas_and_whitespace() {
+    let script = "up, up\n# a comment, left\n/ esc  # trailing comment\ncomma,space,hash";
+    assert!(keys(script) == vec![GameKey::SpecialKey(GameKeyCode::Up),
+                                 GameKey::SpecialKey(GameKeyCode::Up),
+                                 GameKey::Printable('/'),
+                                 GameKey::SpecialKey(GameKeyCode::Escape),
+                                 GameKey::Printable(','),
+                                 GameKey::Printable(' '),
+                                 GameKey::Printable('#')]);
+}
+
+#[test]
+fn unknown_keys_are_rejected() {
+    match ScriptedInputSource::from_script("up,jump,down") {
+        Err(e) => assert_eq!(e, "Unknown key 'jump' in input script"),
+        Ok(_) => panic!("script with an unknown key was accepted")
+    }
+}
+
+#[test]
+fn scripts_round_trip() {
+    let script = "up,down,left,right,shift,esc,none,comma,space,hash,/,a";
+    let keypresses: Vec<KeyboardInput> = keys(script).into_iter().map(KeyboardInput::new).collect();
+    assert_eq!(ScriptedInputSource::to_script(&keypresses), script);
+}
+
+#[test]
+fn same_seed_and_script_replay_the_same() {
+    let script = "up,up,left,/,left,right,down,down,none,none,right,right,right,\
+                  up,up,up,up,left,left,left,up,down,down,down,none";
+    let first = new_game(7, script);
+    let second = new_game(7, script);
+    assert_eq!(first.frames.len(), 26);
+    assert_same(&first, &second);
+    assert_eq!(first.game.input_log.len(), 25);
+
+    let other = new_game(8, script);
+    assert!(first.frames != other.frames);
+}
+
+#[test]
+fn hand_written_saves_replay_the_same() {
+    let records = format!("{}actor enemies 45 25 107 red black 3 3 none random kobold\n", HEROINE);
+    let first = from_save("hand-written", &records, "right,right,up,left");
+    let second = from_save("hand-written", &records, "right,right,up,left");
+    assert_same(&first, &second);
+    assert!(heroine_at(&first.game) != (40, 25));
+}
+
+#[test]
+fn bump_attack() {
+    let records = format!("{}equip main_hand Heroic Sworc\nactor enemies 41 25 107 red black 3 3 none random kobold\n", HEROINE);
+    let first = from_save("bump", &records, "right,right,right");
+    let second = from_save("bump", &records, "right,right,right");
+    assert_same(&first, &second);
+
+    let messages = messages(&first.game);
+    assert!(messages.iter().any(|m| m.contains("the kobold with your Heroic Sworc")));
+    assert!(messages.iter().any(|m| m == "You killed the kobold!"));
+    assert_eq!(first.game.maps.enemies.len(), 0);
+}
+
+#[test]
+fn attack_prompt_matches_bump_attack() {
+    let records = format!("{}equip main_hand Heroic Sworc\nactor enemies 41 25 107 red black 30 30 none random kobold\n", HEROINE);
+    let bumped = from_save("prompt-bumped", &records, "right");
+    let prompted = from_save("prompt-prompted", &records, "a,right");
+    assert_eq!(messages(&bumped.game)[0], messages(&prompted.game)[0]);
+    assert_eq!(bumped.game.maps.clock, prompted.game.maps.clock);
+}
+
+#[test]
+fn stairs() {
+    let records = format!("{}tiles 25 {}>\n", HEROINE, ".".repeat(41));
+    let script = "right,>,none,<";
+    let first = from_save("stairs", &records, script);
+    let second = from_save("stairs", &records, script);
+    assert_same(&first, &second);
+
+    assert!(first.frames[2].contains("You descend to depth 2."));
+    assert!(first.stats[2].iter().any(|line| line == "Depth: 2"));
+    assert_eq!(first.game.maps.depth, 1);
+    assert_eq!(heroine_at(&first.game), (41, 25));
+    assert_eq!(first.game.other_levels.len(), 1);
+}
+
+#[test]
+fn save_and_load_round_trip() {
+    let path = save_path("round-trip");
+    let mut game = new_game(11, "up,left,left,down,right,up").game;
+    game.save_path = path.to_str().unwrap().to_string();
+    game.save();
+
+    // Picking up from the save plays out just like never having stopped
+    let loaded = play(save::load_game(&path, renderer("right,right,up")).unwrap());
+    let straight = new_game(11, "up,left,left,down,right,up,right,right,up");
+    assert_eq!(heroine_at(&loaded.game), heroine_at(&straight.game));
+    assert_eq!(loaded.game.maps.clock, straight.game.maps.clock);
+
+    // Everything but the message window, which says the game was saved
+    let map_and_stats = |frame: &String| frame.lines().take(53).collect::<Vec<&str>>().join("\n");
+    let loaded_frames: Vec<String> = loaded.frames.iter().map(&map_and_stats).collect();
+    let straight_frames: Vec<String> = straight.frames[6..].iter().map(&map_and_stats).collect();
+    assert_eq!(loaded_frames, straight_frames);
+    assert_eq!(&loaded.stats[..], &straight.stats[6..]);
+}
@@ -0,0 +1,168 @@
+extern crate roguelike;
+
+mod common;
+
+use std::fs;
+
+use common::{HEROINE, from_save, messages, renderer};
+use roguelike::game::Game;
+use roguelike::game_states::{FeedGameState, PauseGameState, ThrowGameState, Transition};
+use roguelike::util::Point;
+
+fn tags(game: &Game) -> Vec<String> {
+    game.states.iter().map(|state| state.save_tag()).collect()
+}
+
+#[test]
+fn pop_restores_the_state_underneath() {
+    let mut game = Game::new_with_renderer(renderer(""), 5);
+    game.apply(Transition::Push(Box::new(ThrowGameState::new_with_target(Point::new(3, 4)))));
+    game.apply(Transition::Push(Box::new(FeedGameState::new())));
+    assert_eq!(tags(&game), vec!["movement", "throw 3 4", "feed"]);
+
+    // The aim is where it was left, not a fresh prompt
+    game.apply(Transition::Pop);
+    assert_eq!(tags(&game), vec!["movement", "throw 3 4"]);
+    assert!(game.windows.input.get_messages()[0].contains("Where do you want to throw it?"));
+}
+
+#[test]
+fn pause_over_movement() {
+    let session = from_save("pause", HEROINE, "escape");
+    assert_eq!(tags(&session.game), vec!["movement", "pause"]);
+
+    let session = from_save("pause", HEROINE, "escape,r,right");
+    assert_eq!(tags(&session.game), vec!["movement"]);
+    assert_eq!(session.game.maps.clock, 10);
+    assert_eq!(common::heroine_at(&session.game), (41, 25));
+}
+
+#[test]
+fn pop_never_removes_the_bottom_state() {
+    let mut game = Game::new_with_renderer(renderer(""), 5);
+    game.apply(Transition::Pop);
+    game.apply(Transition::Pop);
+    assert_eq!(tags(&game), vec!["movement"]);
+}
+
+// A kobold next to the heroine, close enough to bite
+fn kobold_scene(name: &str, health: i32, script: &str) -> Game {
+    let records = format!("{}actor enemies 41 25 107 red black 20 20 none aggro kobold\n",
+                          HEROINE.replace("20 20", &format!("{} 20", health)));
+    from_save(name, &records, script).game
+}
+
+#[test]
+fn monsters_hit_the_heroine() {
+    let game = kobold_scene("kobold-hits", 20, "z");
+    let health = game.maps.player().unwrap().health;
+    assert!(health < 20);
+    assert_eq!(messages(&game)[0], format!("The kobold hits you for {}!", 20 - health));
+    assert_eq!(tags(&game), vec!["movement"]);
+}
+
+#[test]
+fn dying_ends_the_run() {
+    let game = kobold_scene("kobold-kills", 1, "z");
+    assert!(game.maps.player_is_dead());
+    assert!(game.game_over);
+    assert_eq!(messages(&game)[0], "You die...");
+    assert!(messages(&game)[1].starts_with("The kobold hits you for "));
+    assert_eq!(tags(&game), vec!["game_over"]);
+
+    // Nothing moves any more
+    let game = kobold_scene("kobold-kills", 1, "z,right,z");
+    assert_eq!(common::heroine_at(&game), (40, 25));
+    assert_eq!(tags(&game), vec!["game_over"]);
+
+    // Until the player goes back to the title screen
+    let game = kobold_scene("kobold-kills", 1, "z,escape");
+    assert_eq!(tags(&game), vec!["title"]);
+    assert!(!game.game_over);
+    assert!(!game.exit);
+}
+
+// Starts on the title screen, saving to and continuing from `save`
+fn at_title(save: &str, script: &str) -> Game {
+    let mut game = Game::new_at_title(renderer(script), 5);
+    game.save_path = common::save_path(save).to_str().unwrap().to_string();
+    common::play(game).game
+}
+
+fn menu(game: &Game) -> Vec<String> {
+    game.windows.map.get_messages().iter().map(|line| line.to_string()).collect()
+}
+
+#[test]
+fn title_menu() {
+    let game = at_title("title", "");
+    assert_eq!(tags(&game), vec!["title"]);
+    assert_eq!(menu(&game)[2], "n) New game");
+    assert!(game.maps.player().is_none());
+
+    let game = at_title("title", "n");
+    assert_eq!(tags(&game), vec!["movement"]);
+    assert!(game.maps.player().is_some());
+    assert!(menu(&game).iter().all(|line| line.is_empty()));
+
+    assert!(at_title("title", "q").exit);
+    assert!(at_title("title", "escape").exit);
+}
+
+#[test]
+fn options_are_toggled_as_they_are_picked() {
+    let game = at_title("options", "o");
+    assert_eq!(tags(&game), vec!["title", "options"]);
+    assert_eq!(menu(&game)[2], "a) Autosave on the stairs: off");
+
+    let game = at_title("options", "o,a");
+    assert!(game.options.autosave);
+    assert_eq!(menu(&game)[2], "a) Autosave on the stairs: on");
+
+    let game = at_title("options", "o,a,a");
+    assert!(!game.options.autosave);
+
+    // Kept on the way back out
+    let game = at_title("options", "o,a,escape");
+    assert_eq!(tags(&game), vec!["title"]);
+    assert!(game.options.autosave);
+    assert_eq!(menu(&game)[2], "n) New game");
+}
+
+#[test]
+fn continue_loads_the_save() {
+    let _ = fs::remove_file(common::save_path("no-save"));
+    let game = at_title("no-save", "c");
+    assert_eq!(tags(&game), vec!["title"]);
+    assert_eq!(messages(&game)[0], "There is no saved game to continue.");
+
+    common::write_save("continue", HEROINE);
+    let game = at_title("continue", "c");
+    assert_eq!(tags(&game), vec!["movement"]);
+    assert_eq!(common::heroine_at(&game), (40, 25));
+}
+
+#[test]
+fn abandoning_goes_back_to_the_title() {
+    let game = at_title("abandon", "n,escape");
+    assert_eq!(tags(&game), vec!["movement", "pause"]);
+
+    let game = at_title("abandon", "n,escape,a");
+    assert_eq!(tags(&game), vec!["title"]);
+    assert_eq!(messages(&game)[0], "You abandon the run.");
+    assert!(game.maps.player().is_none());
+    assert!(!game.exit);
+}
+
+#[test]
+fn save_and_quit() {
+    let _ = fs::remove_file(common::save_path("save-and-quit"));
+    let game = at_title("save-and-quit", "n,right,escape,s");
+    assert!(game.exit);
+    let saved_at = common::heroine_at(&game);
+
+    // Saved as it was played, without the pause menu
+    let game = at_title("save-and-quit", "c");
+    assert_eq!(tags(&game), vec!["movement"]);
+    assert_eq!(common::heroine_at(&game), saved_at);
+}